@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use indextree::NodeId;
+use kern::{Commit, ContentFormat, SourceMap, TitRepository, TitTree};
+
+use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
+use crate::languages::SourceFormat;
+
+pub fn blame(file: &str) -> i32 {
+    let root = match kern::util::find_tit_root() {
+        Some(root) => root,
+        None => panic!("Directory is not part of a tit repository."),
+    };
+    let repository = TitRepository::new(root.clone());
+    let format = SourceFormat::load();
+
+    let relative = match relative_path(&root, file) {
+        Some(relative) => relative,
+        None => {
+            eprintln!("File {} is not part of the repository.", file);
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    let head = match repository.state().current_commit_id() {
+        Some(id) => id.clone(),
+        None => {
+            eprintln!("No commits yet.");
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    let blame = repository.blame(&head);
+    let mut content_path = match blame.file(&relative) {
+        Some(path) => path,
+        None => {
+            eprintln!("File {} is not committed.", relative);
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    // The syntax tree of a parsed file is the first child of the file node, before its source.
+    content_path.push(0);
+    let content = match blame.tree().node_id_at(&content_path) {
+        Some(content) => blame.tree().subtree(content),
+        None => {
+            eprintln!("File {} is not tracked as a syntax tree.", relative);
+            return EXIT_UNSPECIFIED_ERROR;
+        }
+    };
+
+    // Render the committed tree in the formatting of the working copy and parse the result
+    // again to learn which node ended up on which line. Rendering round-trips, so the paths
+    // of both trees agree.
+    let previous = fs::read_to_string(root.join(&relative)).ok();
+    let rendered = format.render(Path::new(&relative), &content, previous.as_deref());
+    let parsed = rendered
+        .as_deref()
+        .and_then(|rendered| format.parse_with_source_map(Path::new(&relative), rendered));
+    let (rendered, (parsed, source_map)) = match (rendered.as_deref(), parsed) {
+        (Some(rendered), Some(parsed)) => (rendered, parsed),
+        _ => {
+            eprintln!("Failed to render {}.", relative);
+            return EXIT_UNSPECIFIED_ERROR;
+        }
+    };
+
+    // Paths of the nodes starting on each line, relative to the whole tree.
+    let mut lines: Vec<Vec<Vec<usize>>> = vec![vec![]; rendered.lines().count()];
+    let mut path = content_path;
+    collect_line_starts(
+        &parsed,
+        parsed.root_id(),
+        &source_map,
+        &mut path,
+        &mut lines,
+    );
+
+    for (number, (line, paths)) in rendered.lines().zip(&lines).enumerate() {
+        let annotation = blame
+            .newest_change(paths.iter().map(|path| path.as_slice()))
+            .map(Commit::shorten_id)
+            .unwrap_or("       ");
+        println!("{} {:>4} | {}", annotation, number + 1, line);
+    }
+
+    EXIT_OK
+}
+
+/// Path of a file relative to the repository root, with `/` as separator.
+fn relative_path(root: &Path, file: &str) -> Option<String> {
+    let absolute = PathBuf::from(file).canonicalize().ok()?;
+    let relative = absolute.strip_prefix(root.canonicalize().ok()?).ok()?;
+    let components: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(components.join("/"))
+}
+
+fn collect_line_starts(
+    tree: &TitTree,
+    node: NodeId,
+    source_map: &SourceMap,
+    path: &mut Vec<usize>,
+    lines: &mut [Vec<Vec<usize>>],
+) {
+    if let Some(span) = source_map.span(node) {
+        let line = source_map.text(0, span.start).matches('\n').count();
+        if let Some(paths) = lines.get_mut(line) {
+            paths.push(path.clone());
+        }
+    }
+
+    for (index, child) in node.children(tree.arena()).enumerate() {
+        path.push(index);
+        collect_line_starts(tree, child, source_map, path, lines);
+        path.pop();
+    }
+}
@@ -0,0 +1,25 @@
+use kern::{BlobRef, TitRepository};
+use network::{NetworkError, TitClient};
+
+/// Downloads the blobs that are missing locally from the current server. Blobs are only
+/// downloaded once their files are needed, so the server is not contacted if none are
+/// missing.
+pub fn download_missing_blobs(
+    repository: &TitRepository,
+    blobs: &[BlobRef],
+) -> Result<(), NetworkError> {
+    let store = repository.blob_store();
+    if blobs
+        .iter()
+        .all(|blob| store.missing_objects(blob).is_empty())
+    {
+        return Ok(());
+    }
+
+    let state = repository.state();
+    let address = state
+        .servers
+        .get(&state.current.server)
+        .ok_or(NetworkError::Unreachable)?;
+    TitClient::new(address, &state.project.name)?.download_blobs(&store, blobs)
+}
@@ -1,15 +1,30 @@
-use kern::TitRepository;
+use kern::TitTree;
 
 use crate::exitcode::EXIT_OK;
 
-pub fn list_changes() -> i32 {
-    let repository = TitRepository::default();
-    let before = repository.signed_tree();
-    let after = repository.current_tree();
-    let difference = before.difference(&after);
+pub fn list_changes(semantic: bool) -> i32 {
+    let repository = crate::languages::repository();
+    let mut before = repository.signed_tree();
+    let mut after = repository.current_tree();
+
+    if semantic {
+        if let Some(format) = repository.content_format() {
+            before = kern::normalize_hash_tree(&before, format);
+            after = kern::normalize_hash_tree(&after, format);
+        }
+    }
+
+    let difference = kern::detect_tree_changes(&before, &after);
+    let renames = kern::summarize_renames(&TitTree::from(&before), &difference);
+
+    for rename in &renames {
+        println!("{}", rename);
+    }
 
     for change in difference {
-        println!("{}", change);
+        if !renames.iter().any(|rename| rename.covers(&change)) {
+            println!("{}", change);
+        }
     }
 
     EXIT_OK
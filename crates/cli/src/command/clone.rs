@@ -0,0 +1,133 @@
+use kern::{TitRepository, DEFAULT_SERVER, NO_COMMIT};
+use network::TitClient;
+use std::{collections::BTreeMap, env::current_dir, fs};
+
+use crate::exitcode::{
+    EXIT_NETWORK_ERROR, EXIT_NOT_FOUND, EXIT_OK, EXIT_UNKNOWN_RESOURCE, EXIT_UNSPECIFIED_ERROR,
+};
+
+const DEFAULT_BRANCH: &str = "main";
+
+pub fn clone(server: &str, project: &str, dir: Option<String>) -> i32 {
+    let working_dir = current_dir().expect("Failed to get current working directory!");
+    let target_dir = working_dir.join(dir.unwrap_or(project.to_string()));
+
+    if target_dir.exists()
+        && target_dir
+            .read_dir()
+            .map_or(true, |mut entries| entries.next().is_some())
+    {
+        eprintln!("Directory {} is not empty.", target_dir.display());
+        return EXIT_UNKNOWN_RESOURCE;
+    }
+
+    let mut checklist = kern::terminal::CheckList::new(&format!(
+        "Cloning project '{}' into '{}'",
+        project,
+        target_dir.display()
+    ));
+
+    checklist.start_step(format!("Contacting server {}.", server));
+    let mut client = match TitClient::new(server, project) {
+        Ok(client) => client,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+
+    checklist.start_step("Downloading index".to_string());
+    let (commits, branches) = match client.download_index() {
+        Ok(index) => index,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    let branches = branches
+        .into_iter()
+        .filter(|(_, commit_id)| commit_id != NO_COMMIT)
+        .collect::<BTreeMap<_, _>>();
+    let branch = if branches.contains_key(DEFAULT_BRANCH) {
+        DEFAULT_BRANCH.to_string()
+    } else {
+        match branches.keys().next() {
+            Some(branch) => branch.clone(),
+            None => {
+                checklist.fail();
+                eprintln!("Project {} has no branches on the server.", project);
+                return EXIT_NOT_FOUND;
+            }
+        }
+    };
+    checklist.finish_step();
+
+    checklist.start_step("Creating repository".to_string());
+    if fs::create_dir_all(&target_dir).is_err() {
+        checklist.fail();
+        return EXIT_UNKNOWN_RESOURCE;
+    }
+    checklist.finish_step();
+
+    let repository = crate::languages::with_languages(TitRepository::new(target_dir));
+    if let Err(err) = repository.init(project, server, &branch) {
+        eprintln!("ERROR: {err}");
+        return EXIT_UNKNOWN_RESOURCE;
+    }
+
+    checklist.start_step(format!("Downloading commits: {} commits", commits.len()));
+    for id in commits {
+        match client.download_commit(id) {
+            Ok(commit) => {
+                if let Err(error) = repository.write_commit(&commit) {
+                    checklist.fail();
+                    eprintln!("Cannot store a downloaded commit: {}", error.0);
+                    return EXIT_UNSPECIFIED_ERROR;
+                }
+            }
+            Err(_) => {
+                checklist.fail();
+                return EXIT_NETWORK_ERROR;
+            }
+        }
+    }
+    checklist.finish_step();
+
+    checklist.start_step("Downloading tags".to_string());
+    match client.download_tags() {
+        Ok(tags) => super::store_server_tags(&repository, tags),
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    }
+    checklist.finish_step();
+
+    checklist.start_step("Updating branches".to_string());
+    let mut state = repository.state();
+    state.branches = branches.clone();
+    state
+        .remote_branches
+        .insert(DEFAULT_SERVER.to_string(), branches);
+    repository.set_state(state);
+    checklist.finish_step();
+
+    checklist.start_step(format!("Checking out branch {}", branch));
+    let state = repository.state();
+    let head = state
+        .current_commit_id()
+        .expect("Cloned branch should have a commit");
+    let blobs = kern::blobs_of_tree(&repository.tree_at(head), &state.sparse_selection());
+    if client
+        .download_blobs(&repository.blob_store(), &blobs)
+        .is_err()
+    {
+        checklist.fail();
+        return EXIT_NETWORK_ERROR;
+    }
+    repository.checkout(head);
+    checklist.finish_step();
+
+    EXIT_OK
+}
@@ -1,23 +1,71 @@
 use kern::util::get_epoch_millis;
+use kern::{TitTree, EMPTY_HASH};
 
-use crate::exitcode::EXIT_OK;
+use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
 
-pub fn commit(message: String) -> i32 {
-    let repository = kern::TitRepository::default();
+pub fn commit(mut message: String) -> i32 {
+    let repository = crate::languages::repository();
+
+    let identity = match repository.identity() {
+        Some(identity) => identity,
+        None => {
+            eprintln!(
+                "Unknown author. Set name and email in the [user] section of {} or of the repository state.",
+                kern::UserConfig::path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or("the user config".to_string())
+            );
+            return EXIT_NOT_FOUND;
+        }
+    };
 
     let before = repository.signed_tree();
     let after = repository.current_tree();
-    let difference = before.difference(&after);
+    let difference = kern::detect_tree_changes(&before, &after);
+    if let Err(error) = repository.store_blobs(&after, &kern::blobs_of_changes(&difference)) {
+        eprintln!("Cannot store the contents of files: {}", error.0);
+        return EXIT_UNSPECIFIED_ERROR;
+    }
+
+    // Renames are summarized in the description, the changes themselves stay untouched.
+    let renames = kern::summarize_renames(&TitTree::from(&before), &difference);
+    if !renames.is_empty() {
+        message.push('\n');
+        for rename in renames {
+            message.push_str(&format!("\n{}", rename));
+        }
+    }
 
     // Test commit handling
-    let commit = kern::Commit::new(message, difference, get_epoch_millis(), None);
-    repository.write_commit(&commit);
+    let predecessor_id = repository.state().current_commit_id().cloned();
+    let tree = after.get_root().map(|root| root.hash).unwrap_or(EMPTY_HASH);
+    let signature = identity.stamp(get_epoch_millis());
+    let mut commit = kern::Commit::new(
+        message,
+        difference,
+        predecessor_id,
+        tree,
+        signature.clone(),
+        signature,
+    );
+    if let Some(key_path) = kern::UserConfig::signing_key_path().filter(|path| path.exists()) {
+        let signed = kern::read_signing_key(&key_path).and_then(|key| commit.sign(&key));
+        if let Err(error) = signed {
+            eprintln!("Cannot sign with {}: {}", key_path.display(), error.0);
+            return EXIT_UNSPECIFIED_ERROR;
+        }
+    }
+    let id = match repository.write_commit(&commit) {
+        Ok(id) => id,
+        Err(error) => {
+            eprintln!("Cannot store the commit: {}", error.0);
+            return EXIT_UNSPECIFIED_ERROR;
+        }
+    };
     println!("Committing: {}", commit);
 
     let mut state = repository.state();
-    state
-        .branches
-        .insert(state.current.branch.clone(), commit.get_id());
+    state.branches.insert(state.current.branch.clone(), id);
     repository.set_state(state);
     repository.set_signed_tree(after);
 
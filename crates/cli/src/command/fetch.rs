@@ -0,0 +1,87 @@
+use kern::TitRepository;
+use network::TitClient;
+
+use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_NOT_FOUND, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
+
+pub fn fetch(server_name: &str) -> i32 {
+    let repository = TitRepository::default();
+    let state = repository.state();
+    let server_address = match state.servers.get(server_name) {
+        Some(address) => address,
+        None => {
+            eprintln!("Server {} not found.", server_name);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    let mut checklist = kern::terminal::CheckList::new(&format!("Fetching from {}", server_name));
+
+    checklist.start_step(format!(
+        "Contacting server {} ({}).",
+        server_name, server_address
+    ));
+    let mut client = match TitClient::new(server_address, &state.project.name) {
+        Ok(client) => client,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+
+    checklist.start_step("Downloading index".to_string());
+    let (commits, branches) = match client.download_index() {
+        Ok(index) => index,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+
+    let local_commits = repository.commit_ids();
+    let missing_commits = commits
+        .into_iter()
+        .filter(|commit| !local_commits.contains(commit))
+        .collect::<Vec<_>>();
+
+    checklist.start_step(format!(
+        "Downloading commits: {} commits",
+        missing_commits.len()
+    ));
+    for id in missing_commits {
+        match client.download_commit(id) {
+            Ok(commit) => {
+                if let Err(error) = repository.write_commit(&commit) {
+                    checklist.fail();
+                    eprintln!("Cannot store a downloaded commit: {}", error.0);
+                    return EXIT_UNSPECIFIED_ERROR;
+                }
+            }
+            Err(_) => {
+                checklist.fail();
+                return EXIT_NETWORK_ERROR;
+            }
+        }
+    }
+    checklist.finish_step();
+
+    checklist.start_step("Downloading tags".to_string());
+    match client.download_tags() {
+        Ok(tags) => super::store_server_tags(&repository, tags),
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    }
+    checklist.finish_step();
+
+    checklist.start_step("Updating remote branches".to_string());
+    let mut state = repository.state();
+    state
+        .remote_branches
+        .insert(server_name.to_string(), branches);
+    repository.set_state(state);
+    checklist.finish_step();
+
+    EXIT_OK
+}
@@ -0,0 +1,22 @@
+use kern::TitRepository;
+
+use crate::exitcode::{EXIT_OK, EXIT_VERIFICATION_FAILED};
+
+pub fn fsck() -> i32 {
+    let repository = TitRepository::default();
+
+    let (checked, problems) = repository.fsck();
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    println!(
+        "Checked {} commits, found {} problems.",
+        checked,
+        problems.len()
+    );
+
+    match problems.is_empty() {
+        true => EXIT_OK,
+        false => EXIT_VERIFICATION_FAILED,
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
+
+pub fn export_git(dir: &str) -> i32 {
+    let repository = crate::languages::repository();
+
+    let blobs = repository.blobs_of_commits(&repository.commit_ids());
+    if super::download_missing_blobs(&repository, &blobs).is_err() {
+        eprintln!("Cannot download the contents of files.");
+        return EXIT_NETWORK_ERROR;
+    }
+
+    match repository.export_git(Path::new(dir)) {
+        Ok(count) => {
+            println!("Exported {} commits into {}", count, dir);
+            EXIT_OK
+        }
+        Err(error) => {
+            eprintln!("Export failed: {}", error.0);
+            EXIT_UNSPECIFIED_ERROR
+        }
+    }
+}
+
+pub fn import_git(path: &str) -> i32 {
+    let repository = crate::languages::repository();
+
+    match repository.import_git(Path::new(path)) {
+        Ok((count, merges)) => {
+            println!("Imported {} commits from {}", count, path);
+            if !merges.is_empty() {
+                println!(
+                    "{} merges only kept their first parent, the history does not lead to the merged branches:",
+                    merges.len()
+                );
+                for merge in merges {
+                    println!("  {}", merge);
+                }
+            }
+            EXIT_OK
+        }
+        Err(error) => {
+            eprintln!("Import failed: {}", error.0);
+            EXIT_UNSPECIFIED_ERROR
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use kern::hashtree::HashTree;
+use kern::{Node, Query, TitRepository, TitTree};
+
+use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
+
+pub fn grep(pattern: &str, ast: bool, commit: Option<String>) -> i32 {
+    let repository = crate::languages::repository();
+
+    let tree = match commit {
+        Some(prefix) => match repository.resolve_commit(&prefix) {
+            Some(id) => repository.tree_at(&id),
+            None => {
+                eprintln!("Commit {} not found.", prefix);
+                return EXIT_NOT_FOUND;
+            }
+        },
+        None => repository.current_tree(),
+    };
+
+    if ast {
+        grep_ast(&repository, pattern, &TitTree::from(&tree))
+    } else {
+        grep_text(&repository, pattern, &tree)
+    }
+}
+
+/// Prints the lines of the files containing the pattern. Files that are not text, or
+/// whose content is not downloaded, are skipped.
+fn grep_text(repository: &TitRepository, pattern: &str, tree: &HashTree<Node>) -> i32 {
+    let mut found = false;
+    for (file, content) in repository.file_contents(tree) {
+        let Some(text) = content
+            .ok()
+            .and_then(|content| String::from_utf8(content).ok())
+        else {
+            continue;
+        };
+        for (number, line) in text.lines().enumerate() {
+            if line.contains(pattern) {
+                println!("{}:{}: {}", file, number + 1, line);
+                found = true;
+            }
+        }
+    }
+
+    if found {
+        EXIT_OK
+    } else {
+        EXIT_NOT_FOUND
+    }
+}
+
+/// Prints the nodes of the syntax trees matching the query.
+fn grep_ast(repository: &TitRepository, pattern: &str, tree: &TitTree) -> i32 {
+    let query = match pattern.parse::<Query>() {
+        Ok(query) => query,
+        Err(error) => {
+            eprintln!("Invalid query: {}", error.0);
+            return EXIT_UNSPECIFIED_ERROR;
+        }
+    };
+
+    let matches = query.matches(tree);
+    if matches.is_empty() {
+        return EXIT_NOT_FOUND;
+    }
+
+    let format = repository.content_format();
+    for (file, node) in matches {
+        let rendered = format
+            .and_then(|format| format.render(Path::new(&file), &tree.subtree(node), None))
+            .unwrap_or_else(|| tree.arena()[node].get().to_string());
+        println!("{}: {}", file, rendered.trim());
+    }
+
+    EXIT_OK
+}
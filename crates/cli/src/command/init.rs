@@ -8,7 +8,7 @@ pub fn init(name: Option<String>, server: Option<String>, branch: Option<String>
     let name = name.unwrap_or(
         working_dir
             .components()
-            .last()
+            .next_back()
             .unwrap()
             .as_os_str()
             .to_str()
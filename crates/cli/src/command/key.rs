@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use kern::{TitRepository, UserConfig, ALL_BRANCHES};
+
+use crate::exitcode::{EXIT_ALREADY_EXISTS, EXIT_NOT_FOUND, EXIT_OK, EXIT_UNSPECIFIED_ERROR};
+
+pub fn generate_key(force: bool) -> i32 {
+    let key = kern::generate_signing_key();
+    store_key(&key, force)
+}
+
+pub fn import_key(file: &str, force: bool) -> i32 {
+    let key = match kern::read_signing_key(Path::new(file)) {
+        Ok(key) => key,
+        Err(error) => {
+            eprintln!("Cannot import {}: {}", file, error.0);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    store_key(&key, force)
+}
+
+/// Trusts signatures by a public key in the repository, e.g. on a server, where branches
+/// that require signatures only accept commits signed by trusted keys.
+pub fn trust_key(public_key: &str, branch: Option<String>) -> i32 {
+    let public_key = public_key.to_lowercase();
+    if kern::util::hex_to_bytes(&public_key).map(|bytes| bytes.len()) != Some(32) {
+        eprintln!("A public key must be 64 hex digits.");
+        return EXIT_UNSPECIFIED_ERROR;
+    }
+
+    let repository = TitRepository::default();
+    let mut state = repository.state();
+    let branch = branch.unwrap_or_else(|| ALL_BRANCHES.to_string());
+    let keys = state.trusted_keys.entry(branch.clone()).or_default();
+    if !keys.insert(public_key) {
+        eprintln!("The key is already trusted on {}.", branch);
+        return EXIT_ALREADY_EXISTS;
+    }
+    repository.set_state(state);
+
+    println!("Trusting the key on {}.", branch);
+    EXIT_OK
+}
+
+fn store_key(key: &kern::SigningKey, overwrite: bool) -> i32 {
+    let path = match UserConfig::signing_key_path() {
+        Some(path) => path,
+        None => {
+            eprintln!("Cannot find the user config directory. Set TIT_CONFIG.");
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    if path.exists() && !overwrite {
+        eprintln!(
+            "A signing key already exists at {}. Use --force to replace it.",
+            path.display()
+        );
+        return EXIT_ALREADY_EXISTS;
+    }
+
+    if let Err(error) = kern::write_signing_key(&path, key) {
+        eprintln!("{}", error);
+        return EXIT_UNSPECIFIED_ERROR;
+    }
+
+    println!("Stored signing key at {}.", path.display());
+    println!("Public key: {}", kern::public_key_hex(key));
+
+    EXIT_OK
+}
@@ -0,0 +1,57 @@
+use kern::{TitRepository, Verification};
+
+use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK};
+
+pub fn log(symbol: Option<String>) -> i32 {
+    let repository = TitRepository::default();
+    let state = repository.state();
+
+    let head = match state.current_commit_id() {
+        Some(id) => id.clone(),
+        None => {
+            eprintln!("No commits yet.");
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    let symbol = match symbol {
+        Some(symbol) => symbol,
+        None => {
+            for id in repository.history(&head) {
+                let commit = repository.read_commit(&id);
+                let verification = commit.verify();
+                match &verification {
+                    Verification::Unsigned => println!("{}", commit),
+                    Verification::Good(key) => {
+                        let trust = match state.trusts_key(&state.current.branch, key) {
+                            true => "trusted",
+                            false => "untrusted",
+                        };
+                        println!("{} [{}, {} key]", commit, verification, trust)
+                    }
+                    Verification::Bad => println!("{} [{}]", commit, verification),
+                }
+            }
+            return EXIT_OK;
+        }
+    };
+
+    let history = repository.symbol_history(&head, &symbol);
+    if history.is_empty() {
+        eprintln!("Symbol {} was never defined.", symbol);
+        return EXIT_NOT_FOUND;
+    }
+
+    for symbol_change in history {
+        println!(
+            "{} in {}",
+            repository.read_commit(&symbol_change.commit_id),
+            symbol_change.file
+        );
+        for change in symbol_change.changes {
+            println!("    {}", change);
+        }
+    }
+
+    EXIT_OK
+}
@@ -1,15 +1,41 @@
+mod blame;
+mod blobs;
 mod branches;
 mod changes;
+mod clone;
 mod commit;
 mod commits;
+mod fetch;
+mod fsck;
+mod git;
+mod grep;
 mod init;
+mod key;
+mod log;
+mod push;
 mod servers;
+mod sparse;
+mod status;
 mod sync;
+mod tags;
 
+pub use blame::*;
+pub use blobs::*;
 pub use branches::*;
 pub use changes::*;
+pub use clone::*;
 pub use commit::*;
 pub use commits::*;
+pub use fetch::*;
+pub use fsck::*;
+pub use git::*;
+pub use grep::*;
 pub use init::*;
+pub use key::*;
+pub use log::*;
+pub use push::*;
 pub use servers::*;
+pub use sparse::*;
+pub use status::*;
 pub use sync::*;
+pub use tags::*;
@@ -0,0 +1,137 @@
+use kern::TitRepository;
+use network::{NetworkError, TitClient};
+
+use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_NOT_FOUND, EXIT_OK, EXIT_REJECTED};
+
+pub fn push(server_name: &str, branch: &str) -> i32 {
+    let repository = TitRepository::default();
+    let state = repository.state();
+    let server_address = match state.servers.get(server_name) {
+        Some(address) => address,
+        None => {
+            eprintln!("Server {} not found.", server_name);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    let commit_id = match state.branches.get(branch) {
+        Some(commit_id) if commit_id != kern::NO_COMMIT => commit_id.clone(),
+        Some(_) => {
+            eprintln!("Branch {} has no commits to push.", branch);
+            return EXIT_NOT_FOUND;
+        }
+        None => {
+            eprintln!("Branch {} not found.", branch);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    let mut checklist =
+        kern::terminal::CheckList::new(&format!("Pushing branch {} to {}", branch, server_name));
+
+    checklist.start_step(format!(
+        "Contacting server {} ({}).",
+        server_name, server_address
+    ));
+    let mut client = match TitClient::new(server_address, &state.project.name) {
+        Ok(client) => client,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+
+    checklist.start_step("Offering commits to server".to_string());
+    let history = repository.history(&commit_id);
+    let missing_commit_ids = match client.offer_content(history.clone()) {
+        Ok(commits) => commits,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+
+    checklist.start_step(format!(
+        "Uploading changes: {} changes",
+        missing_commit_ids.len()
+    ));
+    // The contents of files go first, so the server can serve every commit it has.
+    let blobs = repository.blobs_of_commits(&missing_commit_ids);
+    if client
+        .upload_blobs(&repository.blob_store(), &blobs)
+        .is_err()
+    {
+        checklist.fail();
+        return EXIT_NETWORK_ERROR;
+    }
+    let commits_to_upload = missing_commit_ids
+        .iter()
+        .map(|id| repository.read_commit(id))
+        .collect();
+    match client.upload_changes(commits_to_upload) {
+        Ok(_) => {}
+        Err(NetworkError::Rejected) => {
+            checklist.fail();
+            eprintln!("Server rejected commits with bad signatures. Run `tit fsck` to find them.");
+            return EXIT_REJECTED;
+        }
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    }
+    checklist.finish_step();
+
+    checklist.start_step(format!("Updating branch {}", branch));
+    match client.update_branch(branch.to_string(), commit_id.clone()) {
+        Ok(_) => {}
+        Err(NetworkError::Rejected) => {
+            checklist.fail();
+            eprintln!("Server rejected the update. Fetch and merge the server changes first.");
+            return EXIT_REJECTED;
+        }
+        Err(NetworkError::Unsigned(commits)) => {
+            checklist.fail();
+            eprintln!(
+                "Branch {} only accepts commits signed by trusted keys. Rejected:",
+                branch
+            );
+            for id in commits {
+                eprintln!("    {}", kern::Commit::shorten_id(&id));
+            }
+            return EXIT_REJECTED;
+        }
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    }
+
+    let mut state = repository.state();
+    state
+        .remote_branches
+        .entry(server_name.to_string())
+        .or_default()
+        .insert(branch.to_string(), commit_id);
+    repository.set_state(state);
+    checklist.finish_step();
+
+    checklist.start_step("Uploading tags".to_string());
+    let rejected = match client
+        .download_tags()
+        .map(|server_tags| super::tags_to_upload(&repository, &history, &server_tags))
+        .and_then(|tags| client.upload_tags(tags))
+    {
+        Ok(rejected) => rejected,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    for name in rejected {
+        eprintln!("Server rejected tag {}.", name);
+    }
+    checklist.finish_step();
+
+    EXIT_OK
+}
@@ -1,16 +1,73 @@
-use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK};
+use crate::exitcode::{EXIT_ALREADY_EXISTS, EXIT_NOT_FOUND, EXIT_OK, EXIT_UNKNOWN_RESOURCE};
 
-pub fn add_server(server: &str) -> i32 {
+pub fn add_remote(name: &str, address: &str) -> i32 {
     let repository = kern::TitRepository::default();
     let mut state = repository.state();
 
+    if state.servers.contains_key(name) {
+        eprintln!("Server {} already exists.", name);
+        return EXIT_ALREADY_EXISTS;
+    }
+
     eprintln!(
-        "Adding remote server: {} with project: {}",
-        server,
-        state.project.name.clone()
+        "Adding remote server: {} ({}) with project: {}",
+        name, address, state.project.name
     );
 
-    state.current.server = server.to_string();
+    state.servers.insert(name.to_string(), address.to_string());
+
+    repository.set_state(state);
+
+    EXIT_OK
+}
+
+pub fn remove_remote(name: &str) -> i32 {
+    let repository = kern::TitRepository::default();
+    let mut state = repository.state();
+
+    if !state.servers.contains_key(name) {
+        eprintln!("Server {} not found.", name);
+        return EXIT_NOT_FOUND;
+    }
+
+    if state.current.server == name {
+        eprintln!("Cannot remove the current server {}.", name);
+        return EXIT_UNKNOWN_RESOURCE;
+    }
+
+    state.servers.remove(name);
+    state.remote_branches.remove(name);
+
+    repository.set_state(state);
+
+    EXIT_OK
+}
+
+pub fn rename_remote(name: &str, new_name: &str) -> i32 {
+    let repository = kern::TitRepository::default();
+    let mut state = repository.state();
+
+    if state.servers.contains_key(new_name) {
+        eprintln!("Server {} already exists.", new_name);
+        return EXIT_ALREADY_EXISTS;
+    }
+
+    let address = match state.servers.remove(name) {
+        Some(address) => address,
+        None => {
+            eprintln!("Server {} not found.", name);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    state.servers.insert(new_name.to_string(), address);
+
+    if let Some(branches) = state.remote_branches.remove(name) {
+        state.remote_branches.insert(new_name.to_string(), branches);
+    }
+
+    if state.current.server == name {
+        state.current.server = new_name.to_string();
+    }
 
     repository.set_state(state);
 
@@ -0,0 +1,40 @@
+use kern::SparseSelection;
+
+use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_OK, EXIT_REJECTED};
+
+pub fn set_sparse(paths: &[String]) -> i32 {
+    let repository = crate::languages::repository();
+    let selection = SparseSelection::new(paths);
+
+    let blobs = kern::blobs_of_tree(&repository.signed_tree(), &selection);
+    if super::download_missing_blobs(&repository, &blobs).is_err() {
+        eprintln!("Cannot download the contents of the selected files.");
+        return EXIT_NETWORK_ERROR;
+    }
+
+    if let Err(error) = repository.set_sparse(selection.clone()) {
+        eprintln!("Cannot change the sparse checkout: {}.", error.0);
+        return EXIT_REJECTED;
+    }
+
+    match selection.is_empty() {
+        true => println!("Checked out the whole tree."),
+        false => println!("Checked out {}.", selection.paths().join(", ")),
+    }
+
+    EXIT_OK
+}
+
+pub fn disable_sparse() -> i32 {
+    set_sparse(&[])
+}
+
+pub fn list_sparse() -> i32 {
+    let repository = kern::TitRepository::default();
+
+    for path in repository.state().sparse {
+        println!("{}", path);
+    }
+
+    EXIT_OK
+}
@@ -0,0 +1,106 @@
+use kern::{file_statuses, Commit, FileStatus, RepositoryState, TitRepository};
+
+use crate::exitcode::{EXIT_NOT_FOUND, EXIT_OK};
+
+pub fn status() -> i32 {
+    let repository = crate::languages::repository();
+    let state = repository.state();
+
+    let server_address = match state.servers.get(&state.current.server) {
+        Some(address) => address.clone(),
+        None => {
+            eprintln!("Current server {} not found.", state.current.server);
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    println!(
+        "On branch {} (server {} - {})",
+        state.current.branch, state.current.server, server_address
+    );
+
+    let head = state.current_commit_id().cloned();
+    match &head {
+        Some(id) => println!("Head: {}", repository.read_commit(id)),
+        None => println!("Head: no commits yet"),
+    }
+
+    print_server_comparison(&repository, &state, head.as_deref());
+
+    let before = repository.signed_tree();
+    let after = repository.current_tree();
+    let statuses = file_statuses(&before, &after);
+
+    if statuses.is_empty() {
+        println!();
+        println!("No changes.");
+        return EXIT_OK;
+    }
+
+    print_group(&statuses, "Added", |s| matches!(s, FileStatus::Added(_)));
+    print_group(&statuses, "Modified", |s| {
+        matches!(s, FileStatus::Modified(_))
+    });
+    print_group(&statuses, "Deleted", |s| {
+        matches!(s, FileStatus::Deleted(_))
+    });
+    print_group(&statuses, "Moved", |s| matches!(s, FileStatus::Moved(_, _)));
+
+    EXIT_OK
+}
+
+fn print_server_comparison(
+    repository: &TitRepository,
+    state: &RepositoryState,
+    head: Option<&str>,
+) {
+    let remote_head = match state.remote_commit_id(&state.current.server, &state.current.branch) {
+        Some(id) => id.clone(),
+        None => {
+            println!("Branch is not known on the server. Run fetch to update.");
+            return;
+        }
+    };
+
+    println!("Server head: [{}]", Commit::shorten_id(&remote_head));
+
+    if !repository.commit_ids().contains(&remote_head) {
+        println!("Server has commits that are not downloaded yet. Run fetch to update.");
+        return;
+    }
+
+    let local_history = head.map(|id| repository.history(id)).unwrap_or_default();
+    let remote_history = repository.history(&remote_head);
+
+    let ahead = local_history
+        .iter()
+        .filter(|id| !remote_history.contains(id))
+        .count();
+    let behind = remote_history
+        .iter()
+        .filter(|id| !local_history.contains(id))
+        .count();
+
+    match (ahead, behind) {
+        (0, 0) => println!("Up to date with server."),
+        (ahead, 0) => println!("Ahead of server by {} commit(s).", ahead),
+        (0, behind) => println!("Behind server by {} commit(s).", behind),
+        (ahead, behind) => println!(
+            "Ahead of server by {} and behind by {} commit(s).",
+            ahead, behind
+        ),
+    }
+}
+
+fn print_group(statuses: &[FileStatus], title: &str, filter: impl Fn(&FileStatus) -> bool) {
+    let group = statuses.iter().filter(|s| filter(s)).collect::<Vec<_>>();
+    if group.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("{}:", title);
+    for status in group {
+        println!("    {}", status);
+    }
+}
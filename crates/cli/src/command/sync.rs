@@ -1,7 +1,7 @@
-use kern::TitRepository;
-use network::TitClient;
+use kern::{TitRepository, NO_COMMIT};
+use network::{NetworkError, TitClient};
 
-use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_OK};
+use crate::exitcode::{EXIT_NETWORK_ERROR, EXIT_OK, EXIT_REJECTED, EXIT_UNSPECIFIED_ERROR};
 
 pub fn sync() -> i32 {
     let repository = TitRepository::default();
@@ -23,7 +23,7 @@ pub fn sync() -> i32 {
     };
     checklist.finish_step();
 
-    checklist.start_step(format!("Downloading index"));
+    checklist.start_step("Downloading index".to_string());
     let (commits, branches) = match client.download_index() {
         Ok(index) => index,
         Err(_) => {
@@ -40,7 +40,13 @@ pub fn sync() -> i32 {
         .collect::<Vec<_>>();
     for id in commits {
         match client.download_commit(id) {
-            Ok(commit) => repository.write_commit(&commit),
+            Ok(commit) => {
+                if let Err(error) = repository.write_commit(&commit) {
+                    checklist.fail();
+                    eprintln!("Cannot store a downloaded commit: {}", error.0);
+                    return EXIT_UNSPECIFIED_ERROR;
+                }
+            }
             Err(_) => {
                 checklist.fail();
                 return EXIT_NETWORK_ERROR;
@@ -51,7 +57,7 @@ pub fn sync() -> i32 {
 
     checklist.start_step("Offering changes to server".to_string());
     let local_commits = repository.commit_ids();
-    let missing_commit_ids = match client.offer_content(local_commits, state.branches.clone()) {
+    let missing_commit_ids = match client.offer_content(local_commits) {
         Ok(commits) => commits,
         Err(_) => {
             checklist.fail();
@@ -65,6 +71,14 @@ pub fn sync() -> i32 {
         "Uploading changes: {} changes",
         missing_commit_ids.len()
     ));
+    let blobs = repository.blobs_of_commits(&missing_commit_ids);
+    if client
+        .upload_blobs(&repository.blob_store(), &blobs)
+        .is_err()
+    {
+        checklist.fail();
+        return EXIT_NETWORK_ERROR;
+    }
     let commits_to_upload = missing_commit_ids
         .iter()
         .map(|id| repository.read_commit(id))
@@ -75,15 +89,94 @@ pub fn sync() -> i32 {
     }
     checklist.finish_step();
 
+    checklist.start_step("Syncing tags".to_string());
+    let server_tags = match client.download_tags() {
+        Ok(tags) => tags,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    let tags = super::tags_to_upload(&repository, &repository.commit_ids(), &server_tags);
+    super::store_server_tags(&repository, server_tags);
+    let rejected = match client.upload_tags(tags) {
+        Ok(rejected) => rejected,
+        Err(_) => {
+            checklist.fail();
+            return EXIT_NETWORK_ERROR;
+        }
+    };
+    checklist.finish_step();
+    for name in rejected {
+        eprintln!("Server rejected tag {}.", name);
+    }
+
+    // Branches that are ahead of the server move it forward, branches that are behind take
+    // the head of the server. The server checks every update like a push.
     checklist.start_step("Updating branches".to_string());
     let mut state = repository.state();
-    for (branch, commit) in branches {
-        if state.branches.get(&branch) != Some(&commit) {
-            state.branches.insert(branch, commit);
+    let mut server_branches = branches;
+    let mut diverged = vec![];
+    let mut unsigned = vec![];
+    for (branch, commit_id) in state.branches.clone() {
+        let server_id = server_branches.get(&branch).cloned();
+        if commit_id == NO_COMMIT || server_id.as_ref() == Some(&commit_id) {
+            if let Some(server_id) = server_id {
+                state.branches.insert(branch, server_id);
+            }
+            continue;
+        }
+        if let Some(server_id) = server_id {
+            if repository.history(&server_id).contains(&commit_id) {
+                state.branches.insert(branch, server_id);
+                continue;
+            }
+            if !repository.history(&commit_id).contains(&server_id) {
+                diverged.push(branch);
+                continue;
+            }
+        }
+
+        match client.update_branch(branch.clone(), commit_id.clone()) {
+            Ok(_) => {
+                server_branches.insert(branch, commit_id);
+            }
+            Err(NetworkError::Rejected) => diverged.push(branch),
+            Err(NetworkError::Unsigned(commits)) => unsigned.push((branch, commits)),
+            Err(_) => {
+                checklist.fail();
+                return EXIT_NETWORK_ERROR;
+            }
         }
     }
-    repository.set_state(state.clone());
-    checklist.finish_step();
+    for (branch, server_id) in &server_branches {
+        state
+            .branches
+            .entry(branch.clone())
+            .or_insert_with(|| server_id.clone());
+    }
+    state.remote_branches.insert(server_name, server_branches);
+    repository.set_state(state);
+    if diverged.is_empty() && unsigned.is_empty() {
+        checklist.finish_step();
+        return EXIT_OK;
+    }
 
-    EXIT_OK
+    checklist.fail();
+    for branch in diverged {
+        eprintln!(
+            "Branch {} has diverged from the server. Fetch and merge the server changes first.",
+            branch
+        );
+    }
+    for (branch, commits) in unsigned {
+        eprintln!(
+            "Branch {} only accepts commits signed by trusted keys. Rejected:",
+            branch
+        );
+        for id in commits {
+            eprintln!("    {}", kern::Commit::shorten_id(&id));
+        }
+    }
+    EXIT_REJECTED
 }
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use kern::util::get_epoch_millis;
+use kern::{Tag, TitRepository, Verification};
+
+use crate::exitcode::{
+    EXIT_ALREADY_EXISTS, EXIT_NOT_FOUND, EXIT_OK, EXIT_UNKNOWN_RESOURCE, EXIT_UNSPECIFIED_ERROR,
+};
+
+pub fn create_tag(name: &str, message: Option<String>, commit: Option<String>) -> i32 {
+    let repository = TitRepository::default();
+
+    if !Tag::is_valid_name(name) {
+        eprintln!("{} is not a valid tag name.", name);
+        return EXIT_UNKNOWN_RESOURCE;
+    }
+    if repository.read_tag(name).is_some() {
+        eprintln!("Tag {} already exists.", name);
+        return EXIT_ALREADY_EXISTS;
+    }
+
+    let target = match commit {
+        Some(prefix) => repository.resolve_commit(&prefix),
+        None => repository
+            .state()
+            .current_commit_id()
+            .filter(|id| *id != kern::NO_COMMIT)
+            .cloned(),
+    };
+    let target = match target {
+        Some(target) => target,
+        None => {
+            eprintln!("No commit to tag.");
+            return EXIT_NOT_FOUND;
+        }
+    };
+
+    let tag = match message {
+        None => Tag::lightweight(name.to_string(), target),
+        Some(message) => {
+            let identity = match repository.identity() {
+                Some(identity) => identity,
+                None => {
+                    eprintln!("Unknown tagger. Set name and email in the [user] section of the user config.");
+                    return EXIT_NOT_FOUND;
+                }
+            };
+            let tagger = identity.stamp(get_epoch_millis());
+            let mut tag = Tag::annotated(name.to_string(), target, message, tagger);
+            if let Some(key_path) = kern::UserConfig::signing_key_path().filter(|p| p.exists()) {
+                match kern::read_signing_key(&key_path) {
+                    Ok(key) => tag.sign(&key),
+                    Err(error) => {
+                        eprintln!("Cannot sign with {}: {}", key_path.display(), error.0);
+                        return EXIT_UNSPECIFIED_ERROR;
+                    }
+                }
+            }
+            tag
+        }
+    };
+
+    repository.write_tag(&tag);
+    println!("Tagged: {}", tag);
+
+    EXIT_OK
+}
+
+pub fn list_tags() -> i32 {
+    let repository = TitRepository::default();
+
+    for tag in repository.tags().values() {
+        match tag.verify() {
+            Verification::Unsigned => println!("{}", tag),
+            verification => println!("{} [{}]", tag, verification),
+        }
+    }
+
+    EXIT_OK
+}
+
+/// Stores the tags of a server that are missing locally. Tags do not move, so a local tag
+/// that differs from the one on the server is kept and reported.
+pub fn store_server_tags(repository: &TitRepository, tags: Vec<Tag>) {
+    let local_tags = repository.tags();
+    for tag in tags {
+        match local_tags.get(&tag.name) {
+            Some(local) if *local != tag => {
+                eprintln!(
+                    "Tag {} differs from the one on the server, keeping it.",
+                    tag.name
+                )
+            }
+            Some(_) => {}
+            None if Tag::is_valid_name(&tag.name) => repository.write_tag(&tag),
+            None => eprintln!("Ignoring tag with invalid name {:?}.", tag.name),
+        }
+    }
+}
+
+/// Returns the local tags that point to one of the given commits and are not on the server.
+pub fn tags_to_upload(
+    repository: &TitRepository,
+    commits: &[String],
+    server_tags: &[Tag],
+) -> Vec<Tag> {
+    let server_tags: BTreeMap<_, _> = server_tags.iter().map(|t| (&t.name, t)).collect();
+    repository
+        .tags()
+        .into_values()
+        .filter(|tag| commits.contains(&tag.target) && !server_tags.contains_key(&tag.name))
+        .collect()
+}
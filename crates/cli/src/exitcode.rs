@@ -2,6 +2,8 @@ pub const EXIT_OK: i32 = 0;
 pub const EXIT_UNKNOWN_RESOURCE: i32 = 1;
 pub const EXIT_NETWORK_ERROR: i32 = 2;
 pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_REJECTED: i32 = 4;
+pub const EXIT_VERIFICATION_FAILED: i32 = 5;
+pub const EXIT_ALREADY_EXISTS: i32 = 6;
 
-#[allow(dead_code)]
 pub const EXIT_UNSPECIFIED_ERROR: i32 = 17;
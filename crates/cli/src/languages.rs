@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use kern::{ContentFormat, SourceMap, TitRepository, TitTree};
+use parser::parser::ParsedSource;
+use parser::registry::{LanguageEntry, LanguageRegistry};
+use templater::TitTemplater;
+
+/// Environment variable pointing to the directory with the language configurations, taking
+/// precedence over the one of the installation.
+const LANG_DIR_VAR: &str = "TIT_LANG_DIR";
+
+/// Parses, renders and normalizes source files with the configured languages.
+pub struct SourceFormat {
+    registry: LanguageRegistry,
+    /// The last parse of every file, so a later version of it is parsed incrementally, e.g.
+    /// when a git import parses one commit after the other.
+    parsed: Mutex<HashMap<PathBuf, ParsedSource>>,
+}
+
+impl SourceFormat {
+    pub fn load() -> Self {
+        let registry = std::env::var_os(LANG_DIR_VAR)
+            .map(PathBuf::from)
+            .or_else(installed_lang_dir)
+            .and_then(|lang_dir| LanguageRegistry::load(&lang_dir).ok())
+            .unwrap_or_default();
+        Self {
+            registry,
+            parsed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Parses a file, remembering where each node came from in its contents.
+    pub fn parse_with_source_map(
+        &self,
+        path: &Path,
+        content: &str,
+    ) -> Option<(TitTree, SourceMap)> {
+        let language = self.registry.for_path(path)?;
+        self.parse_file(language, path, content, |parsed| {
+            (parsed.tree().clone(), parsed.source_map().clone())
+        })
+    }
+
+    /// Parses a file, reparsing only what changed since its last parse if there was one,
+    /// and hands the result to `f`.
+    fn parse_file<T>(
+        &self,
+        language: &LanguageEntry,
+        path: &Path,
+        content: &str,
+        f: impl FnOnce(&ParsedSource) -> T,
+    ) -> Option<T> {
+        // Files are parsed in parallel, so the lock is not held while parsing.
+        let previous = self
+            .parsed
+            .lock()
+            .expect("Parse lock poisoned")
+            .remove(path);
+        let mut parser = language.parser().ok()?;
+        let parsed = match previous {
+            Some(previous) => parser.reparse(previous, content),
+            None => parser.parse_source(content),
+        }
+        .ok()?;
+
+        let result = f(&parsed);
+        self.parsed
+            .lock()
+            .expect("Parse lock poisoned")
+            .insert(path.to_path_buf(), parsed);
+        Some(result)
+    }
+}
+
+impl ContentFormat for SourceFormat {
+    fn parse(&self, path: &Path, content: &str) -> Option<TitTree> {
+        let language = self.registry.for_path(path)?;
+        self.parse_file(language, path, content, |parsed| parsed.tree().clone())
+    }
+
+    fn render(&self, path: &Path, tree: &TitTree, previous: Option<&str>) -> Option<String> {
+        let language = self.registry.for_path(path)?;
+        let mut templater = TitTemplater::new(language.template_dir.clone());
+
+        match previous {
+            Some(previous) => {
+                let (mut original, source_map) =
+                    self.parse_file(language, path, previous, |parsed| {
+                        (parsed.tree().clone(), parsed.source_map().clone())
+                    })?;
+                original.apply_changes(&original.detect_changes(tree));
+                templater
+                    .render_tree_preserving(&original, &source_map)
+                    .ok()
+            }
+            None => templater.render_tree(tree).ok(),
+        }
+    }
+
+    fn normalize(&self, path: &Path, tree: &mut TitTree) {
+        if let Some(language) = self.registry.for_path(path) {
+            language.normalize(tree);
+        }
+    }
+}
+
+/// Opens the repository of the working directory, tracking source files as syntax trees.
+pub fn repository() -> TitRepository {
+    with_languages(TitRepository::default())
+}
+
+pub fn with_languages(repository: TitRepository) -> TitRepository {
+    repository.with_content_format(Arc::new(SourceFormat::load()))
+}
+
+/// Finds the language configurations of an installation, either in a `lang` directory next
+/// to the executable or above it, as in a checkout of the sources, or in `share/tit/lang`
+/// of the installation prefix.
+fn installed_lang_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?.canonicalize().ok()?;
+    exe.ancestors()
+        .skip(1)
+        .flat_map(|dir| [dir.join("lang"), dir.join("share/tit/lang")])
+        .find(|dir| dir.is_dir())
+}
@@ -5,6 +5,7 @@ use exitcode::EXIT_UNKNOWN_RESOURCE;
 
 mod command;
 mod exitcode;
+mod languages;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -26,12 +27,89 @@ enum Subcommands {
         branch: Option<String>,
     },
     Uninit,
+    Clone {
+        #[arg(
+            index = 1,
+            name = "server",
+            help = "Address of the server to clone from"
+        )]
+        server: String,
+        #[arg(index = 2, name = "project", help = "Name of the project to clone")]
+        project: String,
+        #[arg(index = 3, name = "dir", help = "Directory to clone into")]
+        dir: Option<String>,
+    },
+    Status,
+    Log {
+        #[arg(long, help = "Only show changes to the named function or type")]
+        symbol: Option<String>,
+    },
+    Grep {
+        #[arg(
+            index = 1,
+            name = "pattern",
+            help = "Text to search for, or a query with --ast"
+        )]
+        pattern: String,
+        #[arg(long, help = "Match the pattern as a query on the syntax trees")]
+        ast: bool,
+        #[arg(
+            long,
+            help = "Search the files of a commit instead of the working tree"
+        )]
+        commit: Option<String>,
+    },
+    Blame {
+        #[arg(index = 1, name = "file", help = "File to annotate")]
+        file: String,
+    },
+    Fsck,
+    ExportGit {
+        #[arg(
+            index = 1,
+            name = "dir",
+            help = "Directory of the git repository to export to"
+        )]
+        dir: String,
+    },
+    ImportGit {
+        #[arg(
+            index = 1,
+            name = "path",
+            help = "Path of the git repository to import"
+        )]
+        path: String,
+    },
+    Tag {
+        #[arg(index = 1, name = "name", help = "Name of the tag")]
+        name: String,
+        #[arg(short, long, help = "Create an annotated tag with this message")]
+        message: Option<String>,
+        #[arg(long, help = "Commit to tag instead of the current one")]
+        commit: Option<String>,
+    },
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
     Sync,
-    Add {
-        #[arg(index = 1, name = "resource", help = "Type of resource to add")]
-        resource: String,
-        #[arg(index = 2, name = "id", help = "Type of resource to add")]
-        id: String,
+    Sparse {
+        #[command(subcommand)]
+        action: SparseAction,
+    },
+    Remote {
+        #[command(subcommand)]
+        action: RemoteAction,
+    },
+    Fetch {
+        #[arg(index = 1, name = "remote", help = "Name of the server to fetch from")]
+        remote: String,
+    },
+    Push {
+        #[arg(index = 1, name = "remote", help = "Name of the server to push to")]
+        remote: String,
+        #[arg(index = 2, name = "branch", help = "Name of the branch to push")]
+        branch: String,
     },
     Create {
         #[arg(index = 1, name = "resource", help = "Type of resource to add")]
@@ -42,6 +120,11 @@ enum Subcommands {
     List {
         #[arg(index = 1, name = "resource", help = "Type of resource to add")]
         resource: String,
+        #[arg(
+            long,
+            help = "Ignore changes that do not alter the meaning of the code"
+        )]
+        semantic: bool,
     },
     Switch {
         #[arg(index = 1, name = "resource", help = "Type of resource to switch")]
@@ -51,6 +134,61 @@ enum Subcommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum KeyAction {
+    Generate {
+        #[arg(long, help = "Replace an existing signing key")]
+        force: bool,
+    },
+    Import {
+        #[arg(index = 1, name = "file", help = "File holding the secret key as hex")]
+        file: String,
+        #[arg(long, help = "Replace an existing signing key")]
+        force: bool,
+    },
+    Trust {
+        #[arg(index = 1, name = "public_key", help = "Public key to trust, as hex")]
+        public_key: String,
+        #[arg(long, help = "Trust the key only on this branch instead of on all")]
+        branch: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SparseAction {
+    Set {
+        #[arg(
+            index = 1,
+            name = "paths",
+            required = true,
+            help = "Directories or files to check out"
+        )]
+        paths: Vec<String>,
+    },
+    List,
+    Disable,
+}
+
+#[derive(Subcommand, Debug)]
+enum RemoteAction {
+    Add {
+        #[arg(index = 1, name = "name", help = "Name of the server")]
+        name: String,
+        #[arg(index = 2, name = "address", help = "Address of the server")]
+        address: String,
+    },
+    Remove {
+        #[arg(index = 1, name = "name", help = "Name of the server")]
+        name: String,
+    },
+    Rename {
+        #[arg(index = 1, name = "name", help = "Current name of the server")]
+        name: String,
+        #[arg(index = 2, name = "new_name", help = "New name of the server")]
+        new_name: String,
+    },
+}
+
 fn main() {
     let cli = Cli::parse();
     let subcommand = cli.command;
@@ -62,21 +200,56 @@ fn main() {
             branch,
         } => command::init(name, server, branch),
         Subcommands::Uninit => command::uninit(),
+        Subcommands::Clone {
+            server,
+            project,
+            dir,
+        } => command::clone(&server, &project, dir),
+        Subcommands::Status => command::status(),
+        Subcommands::Log { symbol } => command::log(symbol),
+        Subcommands::Grep {
+            pattern,
+            ast,
+            commit,
+        } => command::grep(&pattern, ast, commit),
+        Subcommands::Blame { file } => command::blame(&file),
+        Subcommands::Fsck => command::fsck(),
+        Subcommands::ExportGit { dir } => command::export_git(&dir),
+        Subcommands::ImportGit { path } => command::import_git(&path),
+        Subcommands::Tag {
+            name,
+            message,
+            commit,
+        } => command::create_tag(&name, message, commit),
+        Subcommands::Key { action } => match action {
+            KeyAction::Generate { force } => command::generate_key(force),
+            KeyAction::Import { file, force } => command::import_key(&file, force),
+            KeyAction::Trust { public_key, branch } => command::trust_key(&public_key, branch),
+        },
         Subcommands::Sync => command::sync(),
+        Subcommands::Sparse { action } => match action {
+            SparseAction::Set { paths } => command::set_sparse(&paths),
+            SparseAction::List => command::list_sparse(),
+            SparseAction::Disable => command::disable_sparse(),
+        },
         Subcommands::Create { resource, id } => match resource.as_str() {
             "branch" => command::create_branch(&id),
             "change" => command::commit(id),
             _ => EXIT_UNKNOWN_RESOURCE,
         },
-        Subcommands::Add { resource, id } => match resource.as_str() {
-            "server" => command::add_server(&id),
-            _ => EXIT_UNKNOWN_RESOURCE,
+        Subcommands::Remote { action } => match action {
+            RemoteAction::Add { name, address } => command::add_remote(&name, &address),
+            RemoteAction::Remove { name } => command::remove_remote(&name),
+            RemoteAction::Rename { name, new_name } => command::rename_remote(&name, &new_name),
         },
-        Subcommands::List { resource } => match resource.as_str() {
+        Subcommands::Fetch { remote } => command::fetch(&remote),
+        Subcommands::Push { remote, branch } => command::push(&remote, &branch),
+        Subcommands::List { resource, semantic } => match resource.as_str() {
             "commits" => command::list_commits(),
             "servers" => command::list_servers(),
             "branches" => command::list_branches(),
-            "changes" => command::list_changes(),
+            "changes" => command::list_changes(semantic),
+            "tags" => command::list_tags(),
             _ => EXIT_UNKNOWN_RESOURCE,
         },
         Subcommands::Switch { resource, id } => match resource.as_str() {
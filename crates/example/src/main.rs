@@ -1,6 +1,5 @@
-use std::path::PathBuf;
-use parser::c;
-use parser::parser::TitParser;
+use parser::registry::LanguageRegistry;
+use std::path::{Path, PathBuf};
 use templater::TitTemplater;
 
 fn main() {
@@ -11,12 +10,24 @@ fn main() {
         }
     "#;
 
-    let mut parser = TitParser::new(c!()).unwrap();
-    let tree = parser.parse(src).unwrap();
-    
+    let lang_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../lang");
+    let registry = LanguageRegistry::load(&lang_dir).unwrap();
+
+    let (file, src) = match std::env::args().nth(1) {
+        Some(file) => {
+            let src = std::fs::read_to_string(&file).unwrap();
+            (PathBuf::from(file), src)
+        }
+        None => (PathBuf::from("main.c"), src.to_string()),
+    };
+
+    let language = registry.for_path(&file).unwrap();
+    let mut parser = language.parser().unwrap();
+    let tree = parser.parse(&src).unwrap();
+
     println!("{:?}", tree);
 
-    let mut templater = TitTemplater::new(PathBuf::from("/home/phaulson/Desktop/tit/lang/c/templates"));
+    let mut templater = TitTemplater::new(language.template_dir.clone());
     let rendered = templater.render_tree(&tree).unwrap();
     println!("{:?}", rendered);
 }
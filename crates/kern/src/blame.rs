@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use indextree::NodeId;
+
+use crate::repositorytree::KIND_DIR;
+use crate::{Change, Path, TitTree};
+
+/// The tree of a commit together with the commit that last added or updated each of its nodes.
+///
+/// Nodes are followed by their identity in the replayed tree through the changes of every
+/// commit in the history, so a node keeps its blame when deletions before it shift its path,
+/// and reformatting a file, which leaves its tree untouched, does not change the blame.
+#[derive(Debug, Default)]
+pub struct Blame {
+    tree: TitTree,
+    /// Commit ids in the order they were applied, oldest first.
+    commits: Vec<String>,
+    /// Node -> index of the commit that last changed it.
+    changed: HashMap<NodeId, usize>,
+}
+
+impl Blame {
+    /// Applies the changes of the next commit in the history, attributing every node they
+    /// add or update to it.
+    pub fn apply_commit(&mut self, commit_id: &str, changes: &[Change]) {
+        let changed = self.tree.apply_changes_tracked(changes);
+
+        let index = self.commits.len();
+        self.commits.push(commit_id.to_string());
+
+        let arena = self.tree.arena();
+        self.changed.retain(|node, _| !node.is_removed(arena));
+        for node in changed {
+            self.changed.insert(node, index);
+        }
+    }
+
+    pub fn tree(&self) -> &TitTree {
+        &self.tree
+    }
+
+    /// The commit that last added or updated the node at the path.
+    pub fn changed_by(&self, path: &[usize]) -> Option<&str> {
+        let node = self.tree.node_id_at(path)?;
+        self.changed
+            .get(&node)
+            .map(|index| self.commits[*index].as_str())
+    }
+
+    /// The most recent commit that added or updated any of the nodes at the paths.
+    pub fn newest_change<'a>(&self, paths: impl IntoIterator<Item = &'a [usize]>) -> Option<&str> {
+        paths
+            .into_iter()
+            .filter_map(|path| self.tree.node_id_at(path))
+            .filter_map(|node| self.changed.get(&node))
+            .max()
+            .map(|index| self.commits[*index].as_str())
+    }
+
+    /// Finds the path of a file node by the file's path relative to the repository root,
+    /// e.g. `src/main.c`.
+    pub fn file(&self, relative: &str) -> Option<Path> {
+        let arena = self.tree.arena();
+        let mut node = self.tree.root_id();
+        let mut path = vec![0];
+
+        for (depth, name) in relative.split('/').filter(|s| !s.is_empty()).enumerate() {
+            if depth > 0 && arena[node].get().kind != KIND_DIR {
+                return None;
+            }
+
+            let (index, child) = node
+                .children(arena)
+                .enumerate()
+                .find(|(_, child)| arena[*child].get().value.as_deref() == Some(name))?;
+            path.push(index);
+            node = child;
+        }
+
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Blame;
+    use crate::{Change, Node};
+
+    fn node(kind: &str, value: &str) -> Node {
+        Node {
+            kind: kind.to_string(),
+            value: Some(value.to_string()),
+            role: None,
+        }
+    }
+
+    #[test]
+    fn test_blame_follows_nodes() {
+        let mut blame = Blame::default();
+        blame.apply_commit(
+            "first",
+            &[
+                Change::Update(vec![0], node("dir", "root")),
+                Change::Addition(vec![0, 0], node("file", "a.c")),
+                Change::Addition(vec![0, 0, 0], node("identifier", "x")),
+                Change::Addition(vec![0, 0, 1], node("identifier", "y")),
+            ],
+        );
+        blame.apply_commit(
+            "second",
+            &[
+                Change::Update(vec![0, 0, 0], node("identifier", "z")),
+                Change::Deletion(vec![0, 0, 1]),
+            ],
+        );
+        blame.apply_commit(
+            "third",
+            &[Change::Addition(vec![0, 0, 1], node("identifier", "w"))],
+        );
+
+        assert_eq!(blame.file("a.c"), Some(vec![0, 0]));
+        assert_eq!(blame.changed_by(&[0, 0]), Some("first"));
+        assert_eq!(blame.changed_by(&[0, 0, 0]), Some("second"));
+        assert_eq!(blame.changed_by(&[0, 0, 1]), Some("third"));
+        assert_eq!(
+            blame.newest_change([&[0, 0][..], &[0, 0, 0][..]]),
+            Some("second")
+        );
+    }
+
+    #[test]
+    fn test_blame_survives_shifted_paths() {
+        let mut blame = Blame::default();
+        blame.apply_commit(
+            "first",
+            &[
+                Change::Update(vec![0], node("dir", "root")),
+                Change::Addition(vec![0, 0], node("file", "a.c")),
+                Change::Addition(vec![0, 0, 0], node("identifier", "x")),
+                Change::Addition(vec![0, 0, 1], node("identifier", "y")),
+            ],
+        );
+        blame.apply_commit(
+            "second",
+            &[Change::Addition(vec![0, 0, 2], node("identifier", "z"))],
+        );
+        blame.apply_commit("third", &[Change::Deletion(vec![0, 0, 0])]);
+
+        assert_eq!(blame.changed_by(&[0, 0, 0]), Some("first"));
+        assert_eq!(blame.changed_by(&[0, 0, 1]), Some("second"));
+        assert_eq!(blame.changed_by(&[0, 0, 2]), None);
+    }
+}
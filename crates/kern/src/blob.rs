@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fastcdc::v2020::StreamCDC;
+use sha3::Digest;
+
+use crate::filestatus::collect_files;
+use crate::hashtree::HashTree;
+use crate::repositorytree::file_blobs;
+use crate::util::{bytes_to_hex, hex_to_bytes};
+use crate::{CanonicalEncode, Change, Node, NodeHash, SparseSelection, TitError, TitRepository};
+
+/// Kind of the node holding the content of a file that is not parsed.
+pub const KIND_BLOB: &str = "blob";
+
+/// Role of the blob holding the source of a parsed file, next to its syntax tree. Checkouts
+/// render the syntax tree in the formatting of this source.
+pub const ROLE_SOURCE: &str = "source";
+
+/// Bounds of the chunk sizes of content-defined chunking. Cut points depend on the content
+/// only, so an edit in a large file changes the chunks around it and no others.
+const MIN_CHUNK_SIZE: u32 = 16 * 1024;
+const AVG_CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 256 * 1024;
+
+/// Distinguishes temporary files of concurrent writes of the same object.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The content of a file that is not parsed, e.g. an image or an archive, or the source of a
+/// parsed file. The tree only holds its hash and size, the content is kept in the
+/// [`BlobStore`].
+///
+/// The hash is the one of the blob's manifest, which lists the hashes of the chunks the
+/// content is split into, so equal contents have equal hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlobRef {
+    pub hash: NodeHash,
+    pub size: u64,
+}
+
+impl BlobRef {
+    /// Splits a file into chunks and stores them and the manifest, if a store is given.
+    pub fn of_file(path: &Path, store: Option<&BlobStore>) -> io::Result<Self> {
+        Self::of_reader(File::open(path)?, store)
+    }
+
+    /// Like [`BlobRef::of_file`], for content that is already in memory.
+    pub fn of_bytes(content: &[u8], store: Option<&BlobStore>) -> io::Result<Self> {
+        Self::of_reader(content, store)
+    }
+
+    fn of_reader(content: impl Read, store: Option<&BlobStore>) -> io::Result<Self> {
+        let mut manifest = Manifest::default();
+        for chunk in StreamCDC::new(content, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+            let chunk = chunk.map_err(io::Error::other)?;
+            manifest.size += chunk.length as u64;
+            manifest.chunks.push(match store {
+                Some(store) => store.write_object(&chunk.data)?,
+                None => sha3_hash(&chunk.data),
+            });
+        }
+
+        let bytes = manifest.to_canonical_bytes();
+        let hash = match store {
+            Some(store) => store.write_object(&bytes)?,
+            None => sha3_hash(&bytes),
+        };
+        Ok(Self {
+            hash,
+            size: manifest.size,
+        })
+    }
+
+    /// The node standing for the content in the tree, with the hash and size as its value.
+    pub fn to_node(&self) -> Node {
+        Node {
+            kind: KIND_BLOB.to_string(),
+            value: Some(format!("{} {}", bytes_to_hex(&self.hash), self.size)),
+            role: None,
+        }
+    }
+
+    /// The node standing for the source of a parsed file, see [`ROLE_SOURCE`].
+    pub fn to_source_node(&self) -> Node {
+        Node {
+            role: Some(ROLE_SOURCE.to_string()),
+            ..self.to_node()
+        }
+    }
+
+    pub fn from_node(node: &Node) -> Option<Self> {
+        if node.kind != KIND_BLOB {
+            return None;
+        }
+        let (hash, size) = node.value.as_ref()?.split_once(' ')?;
+        Some(Self {
+            hash: hex_to_bytes(hash)?.try_into().ok()?,
+            size: size.parse().ok()?,
+        })
+    }
+}
+
+/// Lists the blobs of the files of a tree that are in the sparse selection, i.e. the blobs
+/// needed to check the tree out.
+pub fn blobs_of_tree(tree: &HashTree<Node>, sparse: &SparseSelection) -> Vec<BlobRef> {
+    collect_files(tree)
+        .into_iter()
+        .filter(|(path, _)| sparse.contains(path))
+        .flat_map(|(_, file)| file_blobs(tree, file))
+        .collect()
+}
+
+/// Lists the blobs that changes add to a tree.
+pub fn blobs_of_changes(changes: &[Change]) -> Vec<BlobRef> {
+    changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Update(_, node) | Change::Addition(_, node) => BlobRef::from_node(node),
+            Change::Deletion(_) => None,
+        })
+        .collect()
+}
+
+/// The chunks of a blob, in order.
+#[derive(Debug, Default)]
+struct Manifest {
+    size: u64,
+    chunks: Vec<NodeHash>,
+}
+
+impl CanonicalEncode for Manifest {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.size.encode(out);
+        self.chunks.encode(out);
+    }
+}
+
+impl Manifest {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let size = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+        let count = u64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?) as usize;
+        let hashes = bytes.get(16..)?;
+        if hashes.len() != count.checked_mul(32)? {
+            return None;
+        }
+        let chunks = hashes
+            .chunks_exact(32)
+            .map(|hash| hash.try_into().expect("Chunks are 32 bytes"))
+            .collect();
+        Some(Self { size, chunks })
+    }
+}
+
+/// Content-addressed storage of blob manifests and chunks, each stored in a file named after
+/// the hash of its content. Objects can be missing, as they are only downloaded when needed.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn object_path(&self, hash: &NodeHash) -> PathBuf {
+        self.dir.join(bytes_to_hex(hash))
+    }
+
+    pub fn contains(&self, hash: &NodeHash) -> bool {
+        self.object_path(hash).exists()
+    }
+
+    pub fn read_object(&self, hash: &NodeHash) -> Option<Vec<u8>> {
+        fs::read(self.object_path(hash)).ok()
+    }
+
+    /// Stores an object and returns its hash. Objects are written to a temporary file
+    /// first, so they are never seen half written.
+    pub fn write_object(&self, data: &[u8]) -> io::Result<NodeHash> {
+        let hash = sha3_hash(data);
+        let path = self.object_path(&hash);
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let temp = path.with_extension(format!(
+            "tmp{}.{}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, data)?;
+        fs::rename(&temp, &path)?;
+        Ok(hash)
+    }
+
+    /// Writes the content of a blob chunk by chunk, so it is never held in memory as a
+    /// whole. Fails if objects of the blob are not stored.
+    pub fn read_into(&self, blob: &BlobRef, out: &mut impl Write) -> io::Result<()> {
+        let not_stored = || io::Error::new(io::ErrorKind::NotFound, "Blob is not downloaded");
+        let manifest = self
+            .read_object(&blob.hash)
+            .and_then(|manifest| Manifest::decode(&manifest))
+            .ok_or_else(not_stored)?;
+        let mut size = 0;
+        for chunk in &manifest.chunks {
+            let data = self.read_object(chunk).ok_or_else(not_stored)?;
+            size += data.len() as u64;
+            out.write_all(&data)?;
+        }
+        match size == manifest.size {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Blob does not match its manifest",
+            )),
+        }
+    }
+
+    /// Writes the content of a blob to a file. The content goes to a temporary file first,
+    /// so the file is never left half written.
+    pub fn read_to_file(&self, blob: &BlobRef, path: &Path) -> io::Result<()> {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        let temp = path.with_file_name(name);
+        let mut file = BufWriter::new(File::create(&temp)?);
+        let written = self
+            .read_into(blob, &mut file)
+            .and_then(|_| file.flush())
+            .and_then(|_| fs::rename(&temp, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written
+    }
+
+    /// Reassembles the content of a blob in memory, if all its objects are stored. Use
+    /// [`BlobStore::read_into`] for contents that are not needed as a whole.
+    pub fn read(&self, blob: &BlobRef) -> Option<Vec<u8>> {
+        let mut content = Vec::with_capacity(blob.size as usize);
+        self.read_into(blob, &mut content).ok()?;
+        Some(content)
+    }
+
+    /// Returns the objects of a blob that are not stored. If the manifest is missing, it is
+    /// the only object returned, as the chunks are only known once it is there.
+    pub fn missing_objects(&self, blob: &BlobRef) -> Vec<NodeHash> {
+        match self
+            .read_object(&blob.hash)
+            .and_then(|m| Manifest::decode(&m))
+        {
+            Some(manifest) => manifest
+                .chunks
+                .into_iter()
+                .filter(|chunk| !self.contains(chunk))
+                .collect(),
+            None => vec![blob.hash],
+        }
+    }
+
+    /// Returns the manifest and the chunks of a blob, as far as they are stored.
+    pub fn objects(&self, blob: &BlobRef) -> Vec<NodeHash> {
+        let chunks = self
+            .read_object(&blob.hash)
+            .and_then(|manifest| Manifest::decode(&manifest))
+            .map(|manifest| manifest.chunks)
+            .unwrap_or_default();
+        std::iter::once(blob.hash)
+            .chain(chunks)
+            .filter(|hash| self.contains(hash))
+            .collect()
+    }
+}
+
+impl TitRepository {
+    pub fn blob_store(&self) -> BlobStore {
+        BlobStore::new(self.dot_tit_dir().join(crate::BLOB_DIR))
+    }
+
+    /// Stores the given blobs of a tree, reading them from the files in the working
+    /// directory the tree was scanned from. Blobs that are stored already are skipped.
+    /// Fails if a file changed since the scan.
+    ///
+    /// Scans only hash the files, so their contents are stored once they are committed, and
+    /// never for files that are not.
+    pub fn store_blobs(
+        &self,
+        tree: &HashTree<Node>,
+        blobs: &[BlobRef],
+    ) -> Result<(), TitError<'static>> {
+        let store = self.blob_store();
+        let wanted: HashSet<_> = blobs.iter().collect();
+        for (path, file) in collect_files(tree) {
+            // Both the blob of an unparsed file and the source of a parsed one hold the
+            // content of the file.
+            let blob = match file_blobs(tree, file).find(|blob| wanted.contains(blob)) {
+                Some(blob) => blob,
+                None => continue,
+            };
+            if store.missing_objects(&blob).is_empty() {
+                continue;
+            }
+            let stored = BlobRef::of_file(&self.root().join(&path), Some(&store))
+                .map_err(|e| TitError("Failed to store file content", Some(e)))?;
+            if stored != blob {
+                return Err(TitError("A file changed since it was scanned", None));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists the blobs the changes of the given commits add, i.e. every version of the
+    /// unparsed files and of the sources of parsed files in their history.
+    pub fn blobs_of_commits(&self, commit_ids: &[String]) -> Vec<BlobRef> {
+        let mut blobs: Vec<_> = commit_ids
+            .iter()
+            .flat_map(|id| blobs_of_changes(&self.read_commit(id).changes))
+            .collect();
+        blobs.sort_by_key(|blob| blob.hash);
+        blobs.dedup();
+        blobs
+    }
+}
+
+fn sha3_hash(data: &[u8]) -> NodeHash {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{blobs_of_tree, BlobRef, BlobStore};
+    use crate::testutil::{init_repository, temp_dir};
+    use crate::SparseSelection;
+    use std::fs;
+
+    #[test]
+    fn test_store_and_read_blob() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        let store = BlobStore::new(dir.join("blobs"));
+
+        // Pseudo-random content, so content-defined chunking finds cut points.
+        let mut state = 1u64;
+        let content: Vec<u8> = (0..1_000_000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 56) as u8
+            })
+            .collect();
+        fs::write(dir.join("a.bin"), &content).unwrap();
+
+        let blob = BlobRef::of_file(&dir.join("a.bin"), Some(&store)).unwrap();
+        assert_eq!(blob.size, content.len() as u64);
+        assert_eq!(BlobRef::from_node(&blob.to_node()), Some(blob));
+        assert_eq!(store.read(&blob), Some(content.clone()));
+        assert!(store.objects(&blob).len() > 2);
+
+        // Inserting a byte at the start only changes the first chunk.
+        let mut edited = content.clone();
+        edited.insert(0, 42);
+        fs::write(dir.join("b.bin"), &edited).unwrap();
+        let objects = store.objects(&blob).len();
+        let other = BlobRef::of_file(&dir.join("b.bin"), Some(&store)).unwrap();
+        assert_eq!(
+            fs::read_dir(store.dir.clone()).unwrap().count(),
+            objects + 2
+        );
+        assert_eq!(store.read(&other), Some(edited.clone()));
+        store.read_to_file(&other, &dir.join("c.bin")).unwrap();
+        assert_eq!(fs::read(dir.join("c.bin")).unwrap(), edited);
+
+        assert_eq!(BlobRef::of_file(&dir.join("a.bin"), None).unwrap(), blob);
+    }
+
+    #[test]
+    fn test_blobs_are_stored_when_asked() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        let repository = init_repository(dir);
+        fs::write(dir.join("a.bin"), "content").unwrap();
+
+        let tree = repository.current_tree();
+        let blobs = blobs_of_tree(&tree, &SparseSelection::default());
+        let store = repository.blob_store();
+        assert_eq!(blobs.len(), 1);
+        assert!(!store.contains(&blobs[0].hash));
+
+        repository.store_blobs(&tree, &blobs).unwrap();
+        assert_eq!(store.read(&blobs[0]), Some(b"content".to_vec()));
+
+        fs::write(dir.join("a.bin"), "changed").unwrap();
+        fs::remove_dir_all(dir.join(crate::DOT_TIT).join(crate::BLOB_DIR)).unwrap();
+        assert!(repository.store_blobs(&tree, &blobs).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::BinaryFile;
+use crate::NodeHash;
+
+/// Identifies the version of a file on disk without reading it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    /// Modification time in nanoseconds since the epoch.
+    pub mtime: u128,
+    pub size: u64,
+    pub inode: u64,
+}
+
+impl From<&Metadata> for FileStamp {
+    fn from(metadata: &Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        Self {
+            mtime,
+            size: metadata.len(),
+            inode,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedFile {
+    pub stamp: FileStamp,
+    /// Hash of the file's node in the tree it was scanned into.
+    pub hash: NodeHash,
+}
+
+/// Remembers the files of the last scan of the working directory, keyed by their path
+/// relative to the repository root.
+///
+/// A file whose stamp is unchanged still has the contents it was scanned with, so if the
+/// signed tree holds a node with the same hash, its syntax tree can be reused instead of
+/// parsing the file again.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TreeCache {
+    files: HashMap<String, CachedFile>,
+}
+
+impl BinaryFile for TreeCache {}
+
+impl TreeCache {
+    pub fn get(&self, path: &str) -> Option<&CachedFile> {
+        self.files.get(path)
+    }
+
+    pub fn insert(&mut self, path: String, file: CachedFile) {
+        self.files.insert(path, file);
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use indextree::Arena;
+
+    use super::TreeCache;
+    use crate::testutil::{temp_dir, write_file};
+    use crate::SparseSelection;
+    use crate::{build_hash_tree_for_dir, build_hash_tree_with_cache, ContentFormat};
+    use crate::{Node, TitTree};
+
+    /// Parses every file into a single node holding its contents.
+    #[derive(Default)]
+    struct TextFormat {
+        parsed: AtomicUsize,
+    }
+
+    impl ContentFormat for TextFormat {
+        fn parse(&self, _path: &Path, content: &str) -> Option<TitTree> {
+            self.parsed.fetch_add(1, Ordering::SeqCst);
+            let mut arena = Arena::new();
+            let root = arena.new_node(Node {
+                kind: "text".to_string(),
+                value: Some(content.to_string()),
+                role: None,
+            });
+            Some(TitTree::new(arena, root))
+        }
+
+        fn render(&self, _path: &Path, _tree: &TitTree, _previous: Option<&str>) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_unchanged_files_are_reused() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        write_file(dir, "a.txt", "a");
+        write_file(dir, "src/b.txt", "b");
+
+        let format = TextFormat::default();
+        let mut cache = TreeCache::default();
+        let first = build_hash_tree_with_cache(
+            dir,
+            Some(&format),
+            &Default::default(),
+            &mut cache,
+            &SparseSelection::default(),
+            None,
+        );
+        assert_eq!(format.parsed.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.len(), 2);
+
+        write_file(dir, "src/b.txt", "changed");
+        let second = build_hash_tree_with_cache(
+            dir,
+            Some(&format),
+            &first,
+            &mut cache,
+            &SparseSelection::default(),
+            None,
+        );
+        assert_eq!(format.parsed.load(Ordering::SeqCst), 3);
+
+        let fresh = build_hash_tree_for_dir(dir, Some(&format));
+        assert_eq!(
+            second.get_root().unwrap().hash,
+            fresh.get_root().unwrap().hash
+        );
+        assert_eq!(TitTree::from(&second), TitTree::from(&fresh));
+    }
+}
@@ -1,16 +1,45 @@
 use crate::change::Change;
-use crate::util::{bytes_to_hex, to_serialized_bytes, BinaryFile};
+use crate::encoding::{CanonicalEncode, NodeHash, EMPTY_HASH};
+use crate::signing::CommitSignature;
+use crate::util::{bytes_to_hex, format_timestamp, from_serialized_bytes, local_offset_minutes};
+use crate::util::{to_serialized_bytes, BinaryFile};
+use crate::TitError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sha3::Digest;
 use std::fmt::{Display, Formatter, Write};
 use std::hash::Hash;
 
+/// Version of the canonical encoding new commits are identified by.
+pub const COMMIT_VERSION: u8 = 2;
+
+/// Who did something to a commit, and when.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PersonStamp {
+    pub name: String,
+    pub email: String,
+    /// Milliseconds since the epoch.
+    pub timestamp: u128,
+    /// Offset of the timezone the time was recorded in, in minutes east of UTC.
+    pub offset: i32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
 pub struct Commit {
+    /// Version of the canonical encoding the ID of this commit is computed from. It is kept
+    /// with the commit, so the way commits are stored can change without changing their IDs.
+    pub version: u8,
     pub message: String,
     pub changes: Vec<Change>,
-    pub timestamp: u128,
     pub predecessor_id: Option<String>,
+    /// Hash of the root of the repository tree after this commit.
+    pub tree: NodeHash,
+    /// Who wrote the changes.
+    pub author: PersonStamp,
+    /// Who recorded the changes in the history, which also dates the commit.
+    pub committer: PersonStamp,
+    /// Signature over the ID of the commit, see [`Commit::sign`].
+    pub signature: Option<CommitSignature>,
 }
 
 impl BinaryFile for Commit {}
@@ -19,25 +48,70 @@ impl Commit {
     pub fn new(
         message: String,
         changes: Vec<Change>,
-        timestamp: u128,
         predecessor_id: Option<String>,
+        tree: NodeHash,
+        author: PersonStamp,
+        committer: PersonStamp,
     ) -> Self {
         Self {
+            version: COMMIT_VERSION,
             message,
             changes,
-            timestamp,
             predecessor_id,
+            tree,
+            author,
+            committer,
+            signature: None,
         }
     }
 
-    pub fn get_id(&self) -> String {
+    /// When the commit was made, in milliseconds since the epoch.
+    pub fn timestamp(&self) -> u128 {
+        self.committer.timestamp
+    }
+
+    /// The SHA3-256 hash of the canonical encoding of the commit, as hex. Fails for
+    /// versions this build does not know, as their encoding is unknown.
+    pub fn get_id(&self) -> Result<String, TitError<'static>> {
+        self.id_hash().map(|hash| bytes_to_hex(&hash))
+    }
+
+    pub(crate) fn id_hash(&self) -> Result<[u8; 32], TitError<'static>> {
+        let bytes = match self.version {
+            0 => to_serialized_bytes(&CommitV0::from(self))
+                .map_err(|_| TitError("Failed to encode commit", None))?,
+            _ => self.canonical_bytes()?,
+        };
         let mut hasher = sha3::Sha3_256::default();
-        let bytes = to_serialized_bytes(&self).expect("Failed to serialize commit");
-        hasher.update(&bytes);
-        let hash = hasher.finalize();
-        let id = bytes_to_hex(&hash);
+        hasher.update(bytes);
+        Ok(hasher.finalize().into())
+    }
+
+    /// Decodes a commit file, including the layouts earlier versions wrote, see
+    /// [`CommitV0`]. A layout is only taken if the commit read with it has the ID it is
+    /// stored under. Otherwise the current layout is used as far as it can be read, so
+    /// damaged commits still show up with the ID they actually have.
+    pub fn decode(bytes: &[u8], id: &str) -> Result<Self, TitError<'static>> {
+        let current = from_serialized_bytes::<Commit>(bytes).ok();
+        if current.as_ref().is_some_and(|commit| commit.has_id(id)) {
+            return Ok(current.expect("Commit was decoded"));
+        }
 
-        id
+        let migrated = [
+            decode_as::<CommitV2>,
+            decode_as::<CommitV1>,
+            decode_as::<CommitV0>,
+        ]
+        .into_iter()
+        .find_map(|decode| decode(bytes).filter(|commit| commit.has_id(id)));
+
+        migrated
+            .or(current)
+            .ok_or(TitError("Failed to decode commit", None))
+    }
+
+    fn has_id(&self, id: &str) -> bool {
+        self.get_id().is_ok_and(|actual| actual == id)
     }
 
     pub fn shorten_id(id: &str) -> &str {
@@ -45,16 +119,281 @@ impl Commit {
     }
 }
 
+impl Commit {
+    /// Commits of version 2 are encoded as, in order:
+    /// - the version, as a single byte,
+    /// - the ID of the predecessor, as an optional string of hex digits,
+    /// - the hash of the tree after the commit, as 32 bytes,
+    /// - the author and the committer, each as their name, email, timestamp in milliseconds
+    ///   since the epoch as a `u128` and timezone offset in minutes as an `i32`,
+    /// - the message, as a string,
+    /// - the changes, as a sequence,
+    ///
+    /// using the primitives described at [`CanonicalEncode`]. Version 1 had no author and
+    /// committer, only the timestamp of the committer. The signature is never encoded, as it
+    /// signs the ID. Version 0 commits are identified by the hash of their file, see
+    /// [`CommitV0`].
+    fn canonical_bytes(&self) -> Result<Vec<u8>, TitError<'static>> {
+        let mut out = Vec::new();
+        self.version.encode(&mut out);
+        self.predecessor_id.encode(&mut out);
+        self.tree.encode(&mut out);
+        match self.version {
+            1 => self.committer.timestamp.encode(&mut out),
+            2 => {
+                self.author.encode(&mut out);
+                self.committer.encode(&mut out);
+            }
+            _ => return Err(TitError("Unknown commit version", None)),
+        }
+        self.message.encode(&mut out);
+        self.changes.encode(&mut out);
+        Ok(out)
+    }
+}
+
+fn decode_as<T: DeserializeOwned + Into<Commit>>(bytes: &[u8]) -> Option<Commit> {
+    from_serialized_bytes::<T>(bytes).ok().map(Into::into)
+}
+
+/// Commit files written before commits had a version. Their ID is the hash of the file,
+/// which is this struct serialized like every binary file. They are read as version 0,
+/// without a tree hash and with an anonymous author and committer.
+#[derive(Serialize, Deserialize)]
+struct CommitV0 {
+    message: String,
+    changes: Vec<Change>,
+    timestamp: u128,
+    predecessor_id: Option<String>,
+}
+
+/// Commit files of version 1, which had no author and committer.
+#[derive(Serialize, Deserialize)]
+struct CommitV1 {
+    version: u8,
+    message: String,
+    changes: Vec<Change>,
+    timestamp: u128,
+    predecessor_id: Option<String>,
+    tree: NodeHash,
+}
+
+/// Commit files of version 2 written before commits could be signed.
+#[derive(Serialize, Deserialize)]
+struct CommitV2 {
+    version: u8,
+    message: String,
+    changes: Vec<Change>,
+    predecessor_id: Option<String>,
+    tree: NodeHash,
+    author: PersonStamp,
+    committer: PersonStamp,
+}
+
+impl From<&Commit> for CommitV0 {
+    fn from(commit: &Commit) -> Self {
+        Self {
+            message: commit.message.clone(),
+            changes: commit.changes.clone(),
+            timestamp: commit.committer.timestamp,
+            predecessor_id: commit.predecessor_id.clone(),
+        }
+    }
+}
+
+impl From<CommitV0> for Commit {
+    fn from(commit: CommitV0) -> Self {
+        let stamp = PersonStamp::anonymous(commit.timestamp);
+        Self {
+            version: 0,
+            message: commit.message,
+            changes: commit.changes,
+            predecessor_id: commit.predecessor_id,
+            tree: EMPTY_HASH,
+            author: stamp.clone(),
+            committer: stamp,
+            signature: None,
+        }
+    }
+}
+
+impl From<CommitV1> for Commit {
+    fn from(commit: CommitV1) -> Self {
+        let stamp = PersonStamp::anonymous(commit.timestamp);
+        Self {
+            version: commit.version,
+            message: commit.message,
+            changes: commit.changes,
+            predecessor_id: commit.predecessor_id,
+            tree: commit.tree,
+            author: stamp.clone(),
+            committer: stamp,
+            signature: None,
+        }
+    }
+}
+
+impl From<CommitV2> for Commit {
+    fn from(commit: CommitV2) -> Self {
+        Self {
+            version: commit.version,
+            message: commit.message,
+            changes: commit.changes,
+            predecessor_id: commit.predecessor_id,
+            tree: commit.tree,
+            author: commit.author,
+            committer: commit.committer,
+            signature: None,
+        }
+    }
+}
+
+impl CanonicalEncode for PersonStamp {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.email.encode(out);
+        self.timestamp.encode(out);
+        self.offset.encode(out);
+    }
+}
+
+impl PersonStamp {
+    /// Stands in for the author and committer of commits that did not record them.
+    fn anonymous(timestamp: u128) -> Self {
+        Self {
+            name: String::new(),
+            email: String::new(),
+            timestamp,
+            offset: 0,
+        }
+    }
+
+    /// The time formatted in the timezone of the machine it is shown on.
+    pub fn local_time(&self) -> String {
+        format_timestamp(self.timestamp, local_offset_minutes(self.timestamp))
+    }
+}
+
+impl Display for PersonStamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} <{}> {}",
+            self.name,
+            self.email,
+            format_timestamp(self.timestamp, self.offset)
+        )
+    }
+}
+
 impl Display for Commit {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_char('[')?;
-        f.write_str(&Self::shorten_id(&self.get_id()))?;
+        match self.get_id() {
+            Ok(id) => f.write_str(Self::shorten_id(&id))?,
+            Err(_) => write!(f, "version {}", self.version)?,
+        }
         f.write_str("] ")?;
         f.write_str(&self.message)?;
         f.write_str(" (")?;
-        f.write_str(&self.timestamp.to_string())?;
+        f.write_str(&self.author.name)?;
+        f.write_str(", ")?;
+        f.write_str(&self.committer.local_time())?;
         f.write_str(") (")?;
         f.write_str(&self.changes.len().to_string())?;
         f.write_str(" changes)")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Commit, CommitV0, CommitV1, CommitV2, PersonStamp};
+    use crate::util::{bytes_to_hex, to_serialized_bytes};
+    use crate::{Change, Node, EMPTY_HASH};
+    use sha3::Digest;
+
+    fn commit(version: u8) -> Commit {
+        let node = Node {
+            kind: "identifier".to_string(),
+            value: Some("x".to_string()),
+            role: None,
+        };
+        let signature = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 1_700_000_000_000,
+            offset: 60,
+        };
+        let mut commit = Commit::new(
+            "first".to_string(),
+            vec![
+                Change::Addition(vec![0, 1], node),
+                Change::Deletion(vec![0, 2]),
+            ],
+            None,
+            EMPTY_HASH,
+            signature.clone(),
+            signature,
+        );
+        commit.version = version;
+        commit
+    }
+
+    #[test]
+    fn test_id_is_stable() {
+        // Changing these breaks the IDs of existing commits, see `Commit::canonical_bytes`.
+        assert_eq!(
+            commit(1).get_id().unwrap(),
+            "9b510757e4771bc5b56d0dbf137f7549e42790cd824bcadbc7c6b4b3dae5df17"
+        );
+        assert_eq!(
+            commit(2).get_id().unwrap(),
+            "22d0a8bf56025274c3f43b20a59d94b4d48c391df6eb088ea604aa1314c1dc52"
+        );
+        assert!(commit(3).get_id().is_err());
+    }
+
+    #[test]
+    fn test_decode_old_layouts() {
+        let v1 = commit(1);
+        let bytes = to_serialized_bytes(&CommitV1 {
+            version: 1,
+            message: v1.message.clone(),
+            changes: v1.changes.clone(),
+            timestamp: v1.timestamp(),
+            predecessor_id: None,
+            tree: EMPTY_HASH,
+        })
+        .unwrap();
+        let decoded = Commit::decode(&bytes, &v1.get_id().unwrap()).unwrap();
+        assert_eq!(decoded.version, 1);
+        assert_eq!(decoded.get_id().unwrap(), v1.get_id().unwrap());
+
+        let v2 = commit(2);
+        let bytes = to_serialized_bytes(&CommitV2 {
+            version: 2,
+            message: v2.message.clone(),
+            changes: v2.changes.clone(),
+            predecessor_id: None,
+            tree: EMPTY_HASH,
+            author: v2.author.clone(),
+            committer: v2.committer.clone(),
+        })
+        .unwrap();
+        let decoded = Commit::decode(&bytes, &v2.get_id().unwrap()).unwrap();
+        assert_eq!(decoded.author, v2.author);
+
+        // Unversioned commits are identified by the hash of their file.
+        let bytes = to_serialized_bytes(&CommitV0::from(&v1)).unwrap();
+        let id = bytes_to_hex(&sha3::Sha3_256::digest(&bytes));
+        let decoded = Commit::decode(&bytes, &id).unwrap();
+        assert_eq!(decoded.version, 0);
+        assert_eq!(decoded.message, "first");
+        assert_eq!(decoded.get_id().unwrap(), id);
+
+        // Commits that match no layout are read as they are, to report their actual ID.
+        let bytes = to_serialized_bytes(&v2).unwrap();
+        let decoded = Commit::decode(&bytes, "0000000").unwrap();
+        assert_eq!(decoded.get_id().unwrap(), v2.get_id().unwrap());
+    }
+}
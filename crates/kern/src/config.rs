@@ -0,0 +1,82 @@
+use std::env;
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::{local_offset_minutes, TomlFile, TomlFileRead};
+use crate::PersonStamp;
+
+/// Environment variable overriding the location of the user config file.
+pub const TIT_CONFIG: &str = "TIT_CONFIG";
+/// Name of the file holding the secret key of the user.
+pub const SIGNING_KEY_FILE: &str = "signing.key";
+
+/// Who makes commits, as configured in the `[user]` section of the user config file or of
+/// the repository state:
+///
+/// ```toml
+/// [user]
+/// name = "Ada Lovelace"
+/// email = "ada@example.com"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    /// Stamps the identity with the given time, in the local timezone.
+    pub fn stamp(&self, timestamp: u128) -> PersonStamp {
+        PersonStamp {
+            name: self.name.clone(),
+            email: self.email.clone(),
+            timestamp,
+            offset: local_offset_minutes(timestamp),
+        }
+    }
+}
+
+impl Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+/// Settings of the user that apply to all repositories.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UserConfig {
+    #[serde(default)]
+    pub user: Option<Identity>,
+}
+
+impl TomlFile for UserConfig {}
+
+impl UserConfig {
+    /// Reads the user config file, or returns an empty config if there is none.
+    pub fn load() -> Self {
+        match Self::path() {
+            Some(path) if path.exists() => UserConfig::read_from(path),
+            _ => UserConfig::default(),
+        }
+    }
+
+    /// `$TIT_CONFIG` if it is set, otherwise `tit/config.toml` in the config directory of
+    /// the user, i.e. `$XDG_CONFIG_HOME` or `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(TIT_CONFIG) {
+            return Some(PathBuf::from(path));
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("tit").join("config.toml"))
+    }
+
+    /// The secret key commits are signed with, next to the user config file.
+    pub fn signing_key_path() -> Option<PathBuf> {
+        Self::path().map(|path| path.with_file_name(SIGNING_KEY_FILE))
+    }
+}
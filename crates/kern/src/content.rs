@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use crate::TitTree;
+
+/// Turns file contents into syntax trees and back, so files can be tracked structurally.
+///
+/// Implemented outside of `kern` by whatever knows the supported languages.
+pub trait ContentFormat: Send + Sync {
+    /// Parses the contents of a file, or returns `None` if its language is not supported.
+    fn parse(&self, path: &Path, content: &str) -> Option<TitTree>;
+
+    /// Renders a syntax tree back to source. The formatting of `previous`, the file's current
+    /// contents, should be kept wherever the tree did not change.
+    fn render(&self, path: &Path, tree: &TitTree, previous: Option<&str>) -> Option<String>;
+
+    /// Rewrites a syntax tree into a canonical form, so that changes which do not alter
+    /// the meaning of the code compare equal.
+    fn normalize(&self, _path: &Path, _tree: &mut TitTree) {}
+}
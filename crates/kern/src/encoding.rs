@@ -0,0 +1,191 @@
+use sha3::Digest;
+
+use crate::{Change, Node};
+
+/// A SHA3-256 digest, e.g. the Merkle hash of a node.
+pub type NodeHash = [u8; 32];
+
+/// Hash of nodes whose hashes are not computed.
+pub const EMPTY_HASH: NodeHash = [0; 32];
+
+/// Writes a value in a canonical binary form that does not depend on the serialization
+/// format of the repository, so hashes over it stay stable.
+///
+/// The encoding is self-delimiting:
+/// - integers are written in little endian with their full width, `usize` as a `u64`,
+/// - strings and sequences start with their length as a `u64`, followed by their elements,
+/// - options start with a `0` byte for `None` or a `1` byte for `Some`,
+/// - fixed-size byte arrays, such as hashes, are written as they are,
+/// - structs are the encodings of their fields in order.
+pub trait CanonicalEncode {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode(&mut out);
+        out
+    }
+}
+
+/// Hashes a node from its value and the hashes of its children, so equal hashes mean equal
+/// subtrees.
+pub fn merkle_hash<T: CanonicalEncode>(
+    value: &T,
+    child_hashes: impl Iterator<Item = NodeHash>,
+) -> NodeHash {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(value.to_canonical_bytes());
+    for child_hash in child_hashes {
+        hasher.update(child_hash);
+    }
+    hasher.finalize().into()
+}
+
+impl<T: CanonicalEncode + ?Sized> CanonicalEncode for &T {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out)
+    }
+}
+
+impl CanonicalEncode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl CanonicalEncode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl CanonicalEncode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl CanonicalEncode for u128 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl CanonicalEncode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+}
+
+impl CanonicalEncode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl CanonicalEncode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+        }
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for [T] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode(out);
+    }
+}
+
+impl<const N: usize> CanonicalEncode for [u8; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl CanonicalEncode for Node {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.kind.encode(out);
+        self.value.encode(out);
+        self.role.encode(out);
+    }
+}
+
+/// Changes start with a tag byte: `0` for updates, `1` for additions and `2` for deletions.
+impl CanonicalEncode for Change {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Change::Update(path, node) => {
+                out.push(0);
+                path.encode(out);
+                node.encode(out);
+            }
+            Change::Addition(path, node) => {
+                out.push(1);
+                path.encode(out);
+                node.encode(out);
+            }
+            Change::Deletion(path) => {
+                out.push(2);
+                path.encode(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merkle_hash, CanonicalEncode};
+    use crate::Node;
+
+    #[test]
+    fn test_encoding_is_unambiguous() {
+        let node = |kind: &str, value: Option<&str>| Node {
+            kind: kind.to_string(),
+            value: value.map(str::to_string),
+            role: None,
+        };
+
+        assert_eq!(
+            node("ab", Some("c")).to_canonical_bytes(),
+            [
+                &[2, 0, 0, 0, 0, 0, 0, 0][..],
+                b"ab",
+                &[1, 1, 0, 0, 0, 0, 0, 0, 0],
+                b"c",
+                &[0]
+            ]
+            .concat()
+        );
+        assert_ne!(
+            node("ab", Some("c")).to_canonical_bytes(),
+            node("a", Some("bc")).to_canonical_bytes()
+        );
+
+        let leaf = merkle_hash(&node("leaf", None), std::iter::empty());
+        assert_ne!(
+            merkle_hash(&node("root", None), [leaf, leaf].into_iter()),
+            merkle_hash(&node("root", None), [leaf].into_iter())
+        );
+    }
+}
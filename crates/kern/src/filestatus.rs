@@ -0,0 +1,164 @@
+use crate::hashtree::{HashTree, HashTreeNode};
+use crate::{Node, NodeHash, KIND_DIR};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileStatus {
+    Added(String),
+    Modified(String),
+    Deleted(String),
+    Moved(String, String),
+}
+
+impl Display for FileStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileStatus::Added(path) => write!(f, "{}", path),
+            FileStatus::Modified(path) => write!(f, "{}", path),
+            FileStatus::Deleted(path) => write!(f, "{}", path),
+            FileStatus::Moved(from, to) => write!(f, "{} -> {}", from, to),
+        }
+    }
+}
+
+/// Compares the files of two directory trees by their relative path.
+///
+/// A file that disappeared from one path and appeared at another with the same
+/// content is reported as moved instead of as a deletion and an addition. The content
+/// is compared by the hashes of the file's subtrees, as the hash of the file node
+/// itself includes its name.
+pub fn file_statuses(before: &HashTree<Node>, after: &HashTree<Node>) -> Vec<FileStatus> {
+    let before_files = collect_files(before);
+    let after_files = collect_files(after);
+
+    let mut statuses = vec![];
+    let mut deleted = vec![];
+    let mut added = vec![];
+
+    for (path, node) in &before_files {
+        match after_files.get(path) {
+            Some(other) if other.hash != node.hash => {
+                statuses.push(FileStatus::Modified(path.clone()))
+            }
+            Some(_) => {}
+            None => deleted.push((path, node)),
+        }
+    }
+
+    for (path, node) in &after_files {
+        if !before_files.contains_key(path) {
+            added.push((path, node));
+        }
+    }
+
+    for (path, node) in deleted {
+        let content = content_hashes(before, node);
+        match added
+            .iter()
+            .position(|(_, other)| content_hashes(after, other) == content)
+        {
+            Some(index) => {
+                let (new_path, _) = added.remove(index);
+                statuses.push(FileStatus::Moved(path.clone(), new_path.clone()));
+            }
+            None => statuses.push(FileStatus::Deleted(path.clone())),
+        }
+    }
+
+    for (path, _) in added {
+        statuses.push(FileStatus::Added(path.clone()));
+    }
+
+    statuses
+}
+
+fn content_hashes(tree: &HashTree<Node>, file: &HashTreeNode<Node>) -> Vec<NodeHash> {
+    file.children
+        .iter()
+        .map(|id| tree.get_node(*id).expect("Child node should exist").hash)
+        .collect()
+}
+
+pub(crate) fn collect_files(tree: &HashTree<Node>) -> BTreeMap<String, &HashTreeNode<Node>> {
+    let mut files = BTreeMap::new();
+    if let Some(root) = tree.get_root() {
+        collect_files_rec(tree, root, "", &mut files);
+    }
+    files
+}
+
+fn collect_files_rec<'a>(
+    tree: &'a HashTree<Node>,
+    node: &'a HashTreeNode<Node>,
+    prefix: &str,
+    files: &mut BTreeMap<String, &'a HashTreeNode<Node>>,
+) {
+    for child_id in &node.children {
+        let child = tree.get_node(*child_id).expect("Child node should exist");
+        let name = child.value.value.clone().unwrap_or_default();
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        if child.value.kind == KIND_DIR {
+            collect_files_rec(tree, child, &path, files);
+        } else {
+            files.insert(path, child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{file_statuses, FileStatus};
+    use crate::hashtree::HashTree;
+    use crate::Node;
+
+    fn node(kind: &str, value: &str) -> Node {
+        Node {
+            kind: kind.to_string(),
+            value: Some(value.to_string()),
+            role: None,
+        }
+    }
+
+    fn file(tree: &mut HashTree<Node>, parent: usize, name: &str, content: &str) {
+        let file = tree.insert(parent, node("file", name)).unwrap();
+        tree.insert(file, node("content", content)).unwrap();
+    }
+
+    #[test]
+    fn test_file_statuses() {
+        let mut before = HashTree::default();
+        let root = before.insert_root(node("dir", "root"));
+        let dir = before.insert(root, node("dir", "src")).unwrap();
+        file(&mut before, dir, "a.c", "a");
+        file(&mut before, root, "b.c", "b");
+        file(&mut before, root, "c.c", "c");
+        file(&mut before, root, "e.c", "e");
+
+        let mut after = HashTree::default();
+        let root = after.insert_root(node("dir", "root"));
+        let dir = after.insert(root, node("dir", "src")).unwrap();
+        file(&mut after, dir, "a.c", "changed");
+        file(&mut after, dir, "b.c", "b");
+        file(&mut after, root, "d.c", "d");
+        file(&mut after, root, "renamed.c", "e");
+
+        let statuses = file_statuses(&before, &after);
+
+        assert_eq!(
+            statuses,
+            vec![
+                FileStatus::Modified("src/a.c".to_string()),
+                FileStatus::Moved("b.c".to_string(), "src/b.c".to_string()),
+                FileStatus::Deleted("c.c".to_string()),
+                FileStatus::Moved("e.c".to_string(), "renamed.c".to_string()),
+                FileStatus::Added("d.c".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+
+use crate::{TitRepository, Verification, NO_COMMIT};
+
+/// Something wrong with the stored history of a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The commit file cannot be read or decoded.
+    Unreadable {
+        id: String,
+    },
+    /// The commit does not hash to the ID it is stored under.
+    WrongId {
+        id: String,
+        actual: String,
+    },
+    BadSignature {
+        id: String,
+    },
+    /// The commit is signed by a key that is not trusted on any branch.
+    UntrustedSignature {
+        id: String,
+        key: String,
+    },
+    MissingPredecessor {
+        id: String,
+        predecessor: String,
+    },
+    MissingBranchHead {
+        branch: String,
+        id: String,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // File names in the commit directory need not be valid IDs.
+        let short = |id: &str| id.get(..7).unwrap_or(id).to_string();
+        match self {
+            Problem::Unreadable { id } => write!(f, "{}: cannot be read", short(id)),
+            Problem::WrongId { id, actual } => {
+                write!(f, "{}: content hashes to {}", short(id), short(actual))
+            }
+            Problem::BadSignature { id } => write!(f, "{}: bad signature", short(id)),
+            Problem::UntrustedSignature { id, key } => {
+                write!(f, "{}: signed by untrusted key {}", short(id), &key[..16])
+            }
+            Problem::MissingPredecessor { id, predecessor } => {
+                write!(
+                    f,
+                    "{}: predecessor {} is missing",
+                    short(id),
+                    short(predecessor)
+                )
+            }
+            Problem::MissingBranchHead { branch, id } => {
+                write!(f, "branch {}: commit {} is missing", branch, short(id))
+            }
+        }
+    }
+}
+
+impl TitRepository {
+    /// Checks that every commit matches its ID, carries a valid signature if it is signed
+    /// and has its predecessor, and that every branch points to an existing commit. Returns
+    /// the number of commits checked and the problems found.
+    ///
+    /// In repositories with trusted keys, signatures by any other key are reported too.
+    pub fn fsck(&self) -> (usize, Vec<Problem>) {
+        let state = self.state();
+        let mut ids = self.commit_ids();
+        ids.sort();
+        let known: HashSet<_> = ids.iter().cloned().collect();
+        let mut problems = vec![];
+
+        for id in &ids {
+            let commit = match self.try_read_commit(id) {
+                Ok(commit) => commit,
+                Err(_) => {
+                    problems.push(Problem::Unreadable { id: id.clone() });
+                    continue;
+                }
+            };
+
+            let actual = match commit.get_id() {
+                Ok(actual) => actual,
+                Err(_) => {
+                    problems.push(Problem::Unreadable { id: id.clone() });
+                    continue;
+                }
+            };
+            if actual != *id {
+                problems.push(Problem::WrongId {
+                    id: id.clone(),
+                    actual,
+                });
+            } else {
+                match commit.verify() {
+                    Verification::Bad => problems.push(Problem::BadSignature { id: id.clone() }),
+                    Verification::Good(key)
+                        if !state.trusted_keys.is_empty() && !state.trusts_key_anywhere(&key) =>
+                    {
+                        problems.push(Problem::UntrustedSignature {
+                            id: id.clone(),
+                            key,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+
+            if let Some(predecessor) = commit.predecessor_id {
+                if !known.contains(&predecessor) {
+                    problems.push(Problem::MissingPredecessor {
+                        id: id.clone(),
+                        predecessor,
+                    });
+                }
+            }
+        }
+
+        for (branch, id) in state.branches {
+            if id != NO_COMMIT && !known.contains(&id) {
+                problems.push(Problem::MissingBranchHead { branch, id });
+            }
+        }
+
+        (ids.len(), problems)
+    }
+}
@@ -0,0 +1,697 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::filestatus::{collect_files, file_statuses};
+use crate::hashtree::HashTree;
+use crate::{build_hash_tree_with_cache, Commit, NodeHash, PersonStamp, Tag, TreeCache};
+use crate::{SparseSelection, TitError, TitRepository, TitTree, EMPTY_HASH, NO_COMMIT};
+
+/// Ref the exported commits are written to before branches and tags point to them.
+const EXPORT_REF: &str = "refs/tit/export";
+
+/// Scratch directory below `.tit` the commits of an imported repository are checked out to.
+const IMPORT_DIR: &str = "import";
+
+/// Files of an exported commit, with the hash of the node each was rendered from.
+type ExportedFiles = BTreeMap<String, NodeHash>;
+
+/// A commit of a git repository to import, see [`TitRepository::import_git`].
+struct GitCommit {
+    id: String,
+    /// Only the first parent of a merge becomes the predecessor, as tit commits have one.
+    parent: Option<String>,
+    is_merge: bool,
+}
+
+impl TitRepository {
+    /// Writes the history of the repository into the git repository at `dir`, creating it if
+    /// needed. Every commit becomes a git commit with the same message, author, committer
+    /// and parent. Its files are rendered from their syntax trees by the content format in
+    /// the formatting of their committed source, unparsed files are exported with the
+    /// content of their blob, all others empty.
+    /// Branches and tags are mapped to git branches and tags. Returns the number of
+    /// exported commits. Fails if blobs of the history are not downloaded.
+    ///
+    /// The export is streamed into `git fast-import`, so `git` must be installed. As the
+    /// export is deterministic, exporting again into the same directory only adds new
+    /// commits and moves the refs forward.
+    pub fn export_git(&self, dir: &Path) -> Result<usize, TitError<'static>> {
+        let blobs = self.blob_store();
+        let missing = self
+            .blobs_of_commits(&self.commit_ids())
+            .iter()
+            .any(|blob| !blobs.missing_objects(blob).is_empty());
+        if missing {
+            return Err(TitError("Contents of files are not downloaded", None));
+        }
+
+        let created = !dir.join(crate::DOT_GIT).exists();
+        let empty = fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_none());
+        if created {
+            fs::create_dir_all(dir)
+                .map_err(|e| TitError("Failed to create export directory", Some(e)))?;
+            git(dir, &["init", "--quiet"])?;
+            // Lets both coexist when exporting into the working directory, like `.git` is
+            // ignored by tit.
+            let exclude = dir.join(crate::DOT_GIT).join("info").join("exclude");
+            fs::create_dir_all(exclude.parent().expect("Exclude file is in a directory"))
+                .and_then(|_| {
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&exclude)
+                })
+                .and_then(|mut file| writeln!(file, "/{}/", crate::DOT_TIT))
+                .map_err(|e| TitError("Failed to exclude the tit directory", Some(e)))?;
+        }
+        // A ref left over from an aborted export would make the import fail.
+        let _ = git(dir, &["update-ref", "-d", EXPORT_REF]);
+
+        let mut import = Command::new("git")
+            .args(["fast-import", "--quiet"])
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| TitError("Failed to run git fast-import", Some(e)))?;
+        let stdin = import.stdin.take().expect("Stdin should be piped");
+
+        let exported = self
+            .write_fast_import(&mut BufWriter::new(stdin))
+            .map_err(|e| TitError("Failed to write to git fast-import", Some(e)))?;
+
+        let status = import
+            .wait()
+            .map_err(|e| TitError("Failed to run git fast-import", Some(e)))?;
+        if !status.success() {
+            return Err(TitError("git fast-import failed", None));
+        }
+        git(dir, &["update-ref", "-d", EXPORT_REF])?;
+
+        // A new repository starts on the current branch. Its files are only checked out into
+        // an empty directory, otherwise just the index is set up and the files in the
+        // directory, e.g. the working tree of this repository, are left as they are.
+        let state = self.state();
+        let head = format!("refs/heads/{}", state.current.branch);
+        if created {
+            git(dir, &["symbolic-ref", "HEAD", &head])?;
+            if state.current_commit_id().is_some_and(|id| id != NO_COMMIT) {
+                match empty {
+                    true => git(dir, &["reset", "--hard", "--quiet"])?,
+                    false => git(dir, &["read-tree", &head])?,
+                }
+            }
+        }
+
+        Ok(exported)
+    }
+
+    /// Adds the history of the git repository at `path` to this repository. The commits of
+    /// all branches are checked out one by one, predecessors first, and their files are
+    /// parsed by the content format. Each becomes a commit with the structural changes from
+    /// the tree of its first parent and the message, author and committer of the git
+    /// commit. Branches are created or moved to the imported commits, and if the current
+    /// branch has no commits yet, the current branch of the git repository is checked out.
+    /// Returns the number of imported commits and the git IDs of the merges among them.
+    ///
+    /// A merge keeps only its first parent as predecessor, its other parents are dropped.
+    /// The commits of merged branches are imported as well, but the history of the merge
+    /// does not lead to them.
+    ///
+    /// Nothing is imported if a branch of the git repository already has commits here, or if
+    /// the working directory would be checked out but has pending changes.
+    ///
+    /// The git repository is read with `git`, which must be installed.
+    pub fn import_git(&self, path: &Path) -> Result<(usize, Vec<String>), TitError<'static>> {
+        let mut state = self.state();
+        let branches = git_output(
+            path,
+            &[
+                "for-each-ref",
+                "--format=%(refname:short) %(objectname)",
+                "refs/heads",
+            ],
+        )?;
+        let has_conflicting_branch = branches.lines().any(|line| {
+            line.split_once(' ')
+                .and_then(|(branch, _)| state.branches.get(branch))
+                .is_some_and(|id| id != NO_COMMIT)
+        });
+        if has_conflicting_branch {
+            return Err(TitError(
+                "A branch of the same name already has commits",
+                None,
+            ));
+        }
+        let checks_out = state.current_commit_id().is_none();
+        if checks_out && !file_statuses(&self.signed_tree(), &self.current_tree()).is_empty() {
+            return Err(TitError("Cannot import with pending changes", None));
+        }
+
+        let commits = git_output(
+            path,
+            &[
+                "rev-list",
+                "--topo-order",
+                "--reverse",
+                "--parents",
+                "--branches",
+            ],
+        )?
+        .lines()
+        .map(|line| {
+            let mut ids = line.split(' ').map(str::to_string);
+            GitCommit {
+                id: ids.next().expect("rev-list prints the commit first"),
+                parent: ids.next(),
+                is_merge: ids.next().is_some(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+        let mut successors: HashMap<&str, usize> = HashMap::new();
+        for commit in &commits {
+            if let Some(parent) = &commit.parent {
+                *successors.entry(parent.as_str()).or_default() += 1;
+            }
+        }
+
+        // The commits are checked out with an index of their own, leaving the one of the
+        // git repository untouched.
+        let work_dir = self.dot_tit_dir().join(IMPORT_DIR);
+        let index_file = self.dot_tit_dir().join(format!("{}.index", IMPORT_DIR));
+        let _ = fs::remove_dir_all(&work_dir);
+        fs::create_dir_all(&work_dir)
+            .map_err(|e| TitError("Failed to create import directory", Some(e)))?;
+        let work_dir = work_dir
+            .canonicalize()
+            .map_err(|e| TitError("Failed to find import directory", Some(e)))?;
+        let index_file = work_dir.with_file_name(index_file.file_name().expect("Has a name"));
+
+        let blobs = self.blob_store();
+        let mut tit_ids: HashMap<&str, String> = HashMap::new();
+        // Trees of imported commits, kept until all their successors are imported.
+        let mut trees: HashMap<&str, TitTree> = HashMap::new();
+        let mut scanned = HashTree::default();
+        let mut cache = TreeCache::default();
+
+        for commit in &commits {
+            let status = Command::new("git")
+                .args(["read-tree", "--reset", "-u", &commit.id])
+                .current_dir(path)
+                .env("GIT_INDEX_FILE", &index_file)
+                .env("GIT_WORK_TREE", &work_dir)
+                .status()
+                .map_err(|e| TitError("Failed to run git", Some(e)))?;
+            if !status.success() {
+                return Err(TitError("Failed to check out git commit", None));
+            }
+            scanned = build_hash_tree_with_cache(
+                &work_dir,
+                self.content_format(),
+                &scanned,
+                &mut cache,
+                &SparseSelection::default(),
+                Some(&blobs),
+            );
+
+            let parent = commit.parent.as_deref();
+            let before = match parent.and_then(|p| trees.get(p)) {
+                Some(tree) => tree.clone(),
+                None => TitTree::default(),
+            };
+            let after = TitTree::from(&scanned);
+            let changes = before.detect_changes(&after);
+
+            let raw = git_output(path, &["cat-file", "commit", &commit.id])?;
+            let (headers, message) = raw.split_once("\n\n").unwrap_or((&raw, ""));
+            let header = |name: &str| {
+                headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                    .and_then(parse_git_signature)
+                    .ok_or(TitError("Malformed git commit", None))
+            };
+            let imported = Commit::new(
+                message.trim_end().to_string(),
+                changes,
+                parent.and_then(|p| tit_ids.get(p)).cloned(),
+                scanned
+                    .get_root()
+                    .map(|root| root.hash)
+                    .unwrap_or(EMPTY_HASH),
+                header("author")?,
+                header("committer")?,
+            );
+            tit_ids.insert(&commit.id, self.write_commit(&imported)?);
+
+            if let Some(parent) = parent {
+                if let Some(remaining) = successors.get_mut(parent) {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        trees.remove(parent);
+                    }
+                }
+            }
+            if successors.contains_key(commit.id.as_str()) {
+                trees.insert(&commit.id, after);
+            }
+        }
+
+        let _ = fs::remove_dir_all(&work_dir);
+        let _ = fs::remove_file(&index_file);
+
+        for line in branches.lines() {
+            if let Some((branch, id)) = line.split_once(' ') {
+                if let Some(tit_id) = tit_ids.get(id) {
+                    state.branches.insert(branch.to_string(), tit_id.clone());
+                }
+            }
+        }
+        if checks_out {
+            let head = git_output(path, &["symbolic-ref", "--short", "HEAD"])?;
+            if state.branches.contains_key(head.trim()) {
+                state.current.branch = head.trim().to_string();
+            }
+        }
+        let head = state.current_commit_id().cloned();
+        self.set_state(state);
+
+        if let Some(head) = head.filter(|id| id != NO_COMMIT) {
+            self.checkout(&head);
+        }
+
+        let merges = commits
+            .iter()
+            .filter(|commit| commit.is_merge)
+            .map(|commit| commit.id.clone())
+            .collect();
+        Ok((commits.len(), merges))
+    }
+
+    /// Writes all commits, branches and tags as a `git fast-import` stream, predecessors
+    /// before their successors. Returns the number of commits written.
+    fn write_fast_import(&self, out: &mut impl Write) -> io::Result<usize> {
+        let commits = self.commits();
+        let order = topological_order(&commits);
+
+        let mut successors: HashMap<&str, usize> = HashMap::new();
+        for commit in commits.values() {
+            if let Some(predecessor) = &commit.predecessor_id {
+                *successors.entry(predecessor.as_str()).or_default() += 1;
+            }
+        }
+
+        let mut marks: HashMap<&str, usize> = HashMap::new();
+        // Trees and files of exported commits, kept until all their successors are exported.
+        let mut exported: HashMap<&str, (TitTree, ExportedFiles)> = HashMap::new();
+
+        for (index, id) in order.iter().enumerate() {
+            let commit = &commits[*id];
+            let predecessor = commit.predecessor_id.as_deref();
+            let (mut tree, before) = match predecessor.and_then(|p| exported.get(p)) {
+                Some((tree, files)) => (tree.clone(), files.clone()),
+                None => (TitTree::default(), ExportedFiles::new()),
+            };
+            tree.apply_changes(&commit.changes);
+
+            let mark = index + 1;
+            let parent = predecessor.and_then(|p| marks.get(p));
+            if parent.is_none() {
+                // Otherwise the commit would continue the previously written one.
+                writeln!(out, "reset {}", EXPORT_REF)?;
+            }
+            writeln!(out, "commit {}", EXPORT_REF)?;
+            writeln!(out, "mark :{}", mark)?;
+            writeln!(out, "author {}", git_signature(&commit.author))?;
+            writeln!(out, "committer {}", git_signature(&commit.committer))?;
+            write_data(out, git_message(&commit.message).as_bytes())?;
+            if let Some(parent) = parent {
+                writeln!(out, "from :{}", parent)?;
+            }
+            let after = self.write_file_changes(out, &tree, &before)?;
+            writeln!(out)?;
+
+            marks.insert(id, mark);
+            if let Some(predecessor) = predecessor {
+                let remaining = successors
+                    .get_mut(predecessor)
+                    .expect("Predecessor is counted");
+                *remaining -= 1;
+                if *remaining == 0 {
+                    exported.remove(predecessor);
+                }
+            }
+            if successors.contains_key(id) {
+                exported.insert(id, (tree, after));
+            }
+        }
+
+        for (branch, id) in self.state().branches {
+            if let Some(mark) = marks.get(id.as_str()) {
+                writeln!(out, "reset refs/heads/{}", branch)?;
+                writeln!(out, "from :{}\n", mark)?;
+            }
+        }
+        for tag in self.tags().values() {
+            if let Some(mark) = marks.get(tag.target.as_str()) {
+                write_tag(out, tag, *mark)?;
+            }
+        }
+        writeln!(out, "done")?;
+        out.flush()?;
+
+        Ok(order.len())
+    }
+
+    /// Writes the files of `tree` that differ from the files of the predecessor, and
+    /// deletions for the files that are gone. Returns the files of `tree`.
+    fn write_file_changes(
+        &self,
+        out: &mut impl Write,
+        tree: &TitTree,
+        before: &ExportedFiles,
+    ) -> io::Result<ExportedFiles> {
+        let tree = HashTree::from(tree);
+        let files = collect_files(&tree);
+
+        for path in before.keys().filter(|path| !files.contains_key(*path)) {
+            writeln!(out, "D {}", quote_path(path))?;
+        }
+
+        let blobs = self.blob_store();
+        let mut after = ExportedFiles::new();
+        for (path, file) in files {
+            if before.get(&path) != Some(&file.hash) {
+                let content = self.file_bytes(&tree, &path, file, None, &blobs)?;
+                writeln!(out, "M 100644 inline {}", quote_path(&path))?;
+                write_data(out, &content)?;
+            }
+            after.insert(path, file.hash);
+        }
+
+        Ok(after)
+    }
+}
+
+/// Orders commits so that every commit follows its predecessor. Commits without a known
+/// predecessor come first, and commits with the same predecessor are ordered by time.
+fn topological_order(commits: &HashMap<String, Commit>) -> Vec<&str> {
+    let mut successors: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    for (id, commit) in commits {
+        let predecessor = commit
+            .predecessor_id
+            .as_deref()
+            .filter(|p| commits.contains_key(*p));
+        successors.entry(predecessor).or_default().push(id);
+    }
+    for ids in successors.values_mut() {
+        ids.sort_by_key(|id| (commits[*id].timestamp(), *id));
+    }
+
+    let mut order = vec![];
+    let mut pending: Vec<&str> = successors.get(&None).cloned().unwrap_or_default();
+    pending.reverse();
+    while let Some(id) = pending.pop() {
+        order.push(id);
+        if let Some(next) = successors.get(&Some(id)) {
+            pending.extend(next.iter().rev());
+        }
+    }
+    order
+}
+
+fn write_tag(out: &mut impl Write, tag: &Tag, mark: usize) -> io::Result<()> {
+    match &tag.annotation {
+        None => {
+            writeln!(out, "reset refs/tags/{}", tag.name)?;
+            writeln!(out, "from :{}\n", mark)
+        }
+        Some(annotation) => {
+            writeln!(out, "tag {}", tag.name)?;
+            writeln!(out, "from :{}", mark)?;
+            writeln!(out, "tagger {}", git_signature(&annotation.tagger))?;
+            write_data(out, git_message(&annotation.message).as_bytes())
+        }
+    }
+}
+
+fn write_data(out: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    writeln!(out, "data {}", data.len())?;
+    out.write_all(data)?;
+    writeln!(out)
+}
+
+/// Messages in git end with a newline, which is dropped on import.
+fn git_message(message: &str) -> String {
+    match message.is_empty() {
+        true => String::new(),
+        false => format!("{}\n", message),
+    }
+}
+
+/// Formats a signature as git does: `Name <email> seconds +hhmm`.
+fn git_signature(signature: &PersonStamp) -> String {
+    let sign = if signature.offset < 0 { '-' } else { '+' };
+    let offset = signature.offset.unsigned_abs();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name,
+        signature.email,
+        signature.timestamp / 1000,
+        sign,
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// Quotes a path the way `git fast-import` reads it, so any file name can be written.
+fn quote_path(path: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parses a signature as git writes it: `Name <email> seconds +hhmm`.
+fn parse_git_signature(line: &str) -> Option<PersonStamp> {
+    let (identity, date) = line.rsplit_once('>')?;
+    let (name, email) = identity.split_once('<')?;
+    let (seconds, offset) = date.trim().split_once(' ')?;
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let offset = offset.trim_start_matches(['+', '-']);
+    let hours: i32 = offset.get(..2)?.parse().ok()?;
+    let minutes: i32 = offset.get(2..)?.parse().ok()?;
+
+    Some(PersonStamp {
+        name: name.trim().to_string(),
+        email: email.to_string(),
+        timestamp: seconds.parse::<u128>().ok()? * 1000,
+        offset: sign * (hours * 60 + minutes),
+    })
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Result<String, TitError<'static>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| TitError("Failed to run git", Some(e)))?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(TitError("git command failed", None)),
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<(), TitError<'static>> {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .map_err(|e| TitError("Failed to run git", Some(e)))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(TitError("git command failed", None)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::{git_signature, parse_git_signature, quote_path};
+    use crate::testutil::{init_repository, temp_dir, write_file};
+    use crate::PersonStamp;
+
+    /// Runs git with a fixed identity and dates and without the config of the user.
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "Ada")
+            .env("GIT_AUTHOR_EMAIL", "ada@example.com")
+            .env("GIT_AUTHOR_DATE", "1700000000 +0200")
+            .env("GIT_COMMITTER_NAME", "Bob")
+            .env("GIT_COMMITTER_EMAIL", "bob@example.com")
+            .env("GIT_COMMITTER_DATE", "1700000100 -0530")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_git_round_trip() {
+        if Command::new("git").arg("--version").output().is_err() {
+            println!("Skipping, git is not installed");
+            return;
+        }
+
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        let source = dir.join("source");
+        fs::create_dir_all(&source).unwrap();
+        run_git(&source, &["init", "--quiet", "--initial-branch=main"]);
+        write_file(&source, "README.md", "hello\n");
+        write_file(&source, "src/lib.txt", "a\nb\n");
+        run_git(&source, &["add", "--all"]);
+        run_git(
+            &source,
+            &["commit", "--quiet", "-m", "First\n\nWith a body."],
+        );
+        write_file(&source, "src/lib.txt", "a\nc\n");
+        write_file(&source, "docs/guide.txt", "read me\n");
+        run_git(&source, &["add", "--all"]);
+        run_git(&source, &["commit", "--quiet", "-m", "Second"]);
+        run_git(&source, &["checkout", "--quiet", "-b", "feature", "HEAD~1"]);
+        fs::remove_file(source.join("README.md")).unwrap();
+        run_git(&source, &["add", "--all"]);
+        run_git(&source, &["commit", "--quiet", "-m", "Feature"]);
+        run_git(&source, &["checkout", "--quiet", "main"]);
+
+        let repository = init_repository(&dir.join("tit"));
+        assert_eq!(repository.import_git(&source).unwrap(), (3, vec![]));
+        assert_eq!(
+            fs::read_to_string(dir.join("tit/src/lib.txt")).unwrap(),
+            "a\nc\n"
+        );
+        // The branches exist now, so importing again would move them.
+        assert!(repository.import_git(&source).is_err());
+
+        let exported = dir.join("exported");
+        assert_eq!(repository.export_git(&exported).unwrap(), 3);
+        let refs = ["for-each-ref", "--format=%(refname:short)", "refs/heads"];
+        assert_eq!(run_git(&exported, &refs), run_git(&source, &refs));
+        for branch in ["main", "feature"] {
+            let tree = format!("{}^{{tree}}", branch);
+            assert_eq!(
+                run_git(&exported, &["rev-parse", &tree]),
+                run_git(&source, &["rev-parse", &tree])
+            );
+            let log = [
+                "log",
+                "--format=%B%an %ae %ad %cn %ce %cd",
+                "--date=raw",
+                branch,
+            ];
+            assert_eq!(run_git(&exported, &log), run_git(&source, &log));
+        }
+
+        // Importing would check out the files of main over the pending changes.
+        let dirty = init_repository(&dir.join("dirty"));
+        write_file(&dir.join("dirty"), "src/lib.txt", "mine\n");
+        assert!(dirty.import_git(&source).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("dirty/src/lib.txt")).unwrap(),
+            "mine\n"
+        );
+    }
+
+    #[test]
+    fn test_git_import_reports_merges() {
+        if Command::new("git").arg("--version").output().is_err() {
+            println!("Skipping, git is not installed");
+            return;
+        }
+
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        let source = dir.join("source");
+        fs::create_dir_all(&source).unwrap();
+        run_git(&source, &["init", "--quiet", "--initial-branch=main"]);
+        write_file(&source, "README.md", "hello\n");
+        run_git(&source, &["add", "--all"]);
+        run_git(&source, &["commit", "--quiet", "-m", "First"]);
+        run_git(&source, &["checkout", "--quiet", "-b", "feature"]);
+        write_file(&source, "feature.txt", "feature\n");
+        run_git(&source, &["add", "--all"]);
+        run_git(&source, &["commit", "--quiet", "-m", "Feature"]);
+        run_git(&source, &["checkout", "--quiet", "main"]);
+        write_file(&source, "main.txt", "main\n");
+        run_git(&source, &["add", "--all"]);
+        run_git(&source, &["commit", "--quiet", "-m", "Main"]);
+        run_git(
+            &source,
+            &["merge", "--quiet", "--no-ff", "-m", "Merge", "feature"],
+        );
+        let merge = run_git(&source, &["rev-parse", "main"]);
+
+        let repository = init_repository(&dir.join("tit"));
+        assert_eq!(
+            repository.import_git(&source).unwrap(),
+            (4, vec![merge.trim().to_string()])
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("tit/feature.txt")).unwrap(),
+            "feature\n"
+        );
+
+        // The history of the merge leads to the first parent only.
+        let mut messages = vec![];
+        let mut id = repository.state().current_commit_id().cloned();
+        while let Some(commit) = id.map(|id| repository.read_commit(&id)) {
+            messages.push(commit.message.trim().to_string());
+            id = commit.predecessor_id;
+        }
+        assert_eq!(messages, ["Merge", "Main", "First"]);
+    }
+
+    #[test]
+    fn test_fast_import_formatting() {
+        let signature = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 1_700_000_000_999,
+            offset: -330,
+        };
+        assert_eq!(
+            git_signature(&signature),
+            "Ada <ada@example.com> 1700000000 -0530"
+        );
+        assert_eq!(
+            parse_git_signature("Ada <ada@example.com> 1700000000 -0530"),
+            Some(PersonStamp {
+                timestamp: 1_700_000_000_000,
+                ..signature
+            })
+        );
+        assert_eq!(quote_path("src/a \"b\".c"), "\"src/a \\\"b\\\".c\"");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::encoding::{NodeHash, EMPTY_HASH};
 use crate::util::bytes_to_hex;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug};
@@ -5,7 +6,7 @@ use std::fmt::{self, Debug};
 #[derive(Serialize, Deserialize)]
 pub struct HashTreeNode<T> {
     pub value: T,
-    pub hash: u64,
+    pub hash: NodeHash,
     pub parent: Option<usize>,
     pub children: Vec<usize>,
 }
@@ -25,7 +26,7 @@ impl<T> HashTreeNode<T> {
         Self {
             parent,
             value,
-            hash: 0,
+            hash: EMPTY_HASH,
             children,
         }
     }
@@ -33,11 +34,6 @@ impl<T> HashTreeNode<T> {
 
 impl<T: Debug> Debug for HashTreeNode<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{:?} ({})",
-            self.value,
-            bytes_to_hex(&self.hash.to_le_bytes())
-        )
+        write!(f, "{:?} ({})", self.value, bytes_to_hex(&self.hash))
     }
 }
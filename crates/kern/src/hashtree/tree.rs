@@ -1,11 +1,9 @@
 use super::{node::HashTreeNode, slot::Slot};
+use crate::encoding::{merkle_hash, CanonicalEncode, NodeHash, EMPTY_HASH};
 use crate::{util::BinaryFile, Change, Node};
 use core::panic;
 use serde::{Deserialize, Serialize};
-use std::{
-    fmt::Debug,
-    hash::{DefaultHasher, Hash, Hasher},
-};
+use std::fmt::Debug;
 
 #[derive(Serialize, Deserialize)]
 pub struct HashTree<T> {
@@ -28,23 +26,21 @@ impl<T> Default for HashTree<T> {
     }
 }
 
-impl<T: Hash> HashTree<T> {
-    pub fn compute_hash(&self, node: &HashTreeNode<T>) -> u64 {
+#[allow(clippy::result_unit_err)]
+impl<T: CanonicalEncode> HashTree<T> {
+    pub fn compute_hash(&self, node: &HashTreeNode<T>) -> NodeHash {
         if !self.should_compute_hashes {
-            return 0;
+            return EMPTY_HASH;
         }
 
-        let mut hasher = DefaultHasher::new();
-        node.value.hash(&mut hasher);
-        for child in &node.children {
-            match self.values.get(*child) {
-                Some(Slot::Filled { item: child }) => {
-                    child.hash.hash(&mut hasher);
-                }
+        let child_hashes = node
+            .children
+            .iter()
+            .map(|child| match self.values.get(*child) {
+                Some(Slot::Filled { item: child }) => child.hash,
                 _ => panic!("Child node not found"),
-            }
-        }
-        hasher.finish()
+            });
+        merkle_hash(&node.value, child_hashes)
     }
 
     pub fn refresh_hash_at(&mut self, id: usize) {
@@ -59,10 +55,9 @@ impl<T: Hash> HashTree<T> {
 
         let hash = self.compute_hash(node);
 
-        match self.values.get_mut(id) {
-            Some(Slot::Filled { item }) => item.hash = hash,
-            _ => return,
-        };
+        if let Some(Slot::Filled { item }) = self.values.get_mut(id) {
+            item.hash = hash
+        }
     }
 
     pub fn insert(&mut self, parent: usize, value: T) -> Result<usize, ()> {
@@ -77,8 +72,8 @@ impl<T: Hash> HashTree<T> {
 
         // update parent
         match parent
-            .map_or(None, |parent| self.values.get_mut(parent))
-            .map_or(None, |s| match s {
+            .and_then(|parent| self.values.get_mut(parent))
+            .and_then(|s| match s {
                 Slot::Filled { item } => Some(item),
                 _ => None,
             }) {
@@ -93,6 +88,45 @@ impl<T: Hash> HashTree<T> {
         Ok(index)
     }
 
+    /// Copies the subtree of a node of another tree below `parent`, keeping the hashes it
+    /// already has, so only the branch it is inserted into needs to be hashed again.
+    pub fn insert_subtree(
+        &mut self,
+        parent: usize,
+        source: &HashTree<T>,
+        node: &HashTreeNode<T>,
+    ) -> Result<usize, ()>
+    where
+        T: Clone,
+    {
+        self.get_node(parent).ok_or(())?;
+        let index = self.copy_subtree(parent, source, node)?;
+        self.update_hashes_of_branch(Some(parent));
+        Ok(index)
+    }
+
+    fn copy_subtree(
+        &mut self,
+        parent: usize,
+        source: &HashTree<T>,
+        node: &HashTreeNode<T>,
+    ) -> Result<usize, ()>
+    where
+        T: Clone,
+    {
+        let mut copy = HashTreeNode::new(node.value.clone(), Some(parent));
+        copy.hash = node.hash;
+        let index = self.insert_at_free_space(copy);
+        self.get_node_mut(parent).ok_or(())?.children.push(index);
+
+        for child_id in &node.children {
+            let child = source.get_node(*child_id).ok_or(())?;
+            self.copy_subtree(index, source, child)?;
+        }
+
+        Ok(index)
+    }
+
     fn insert_at_free_space(&mut self, node: HashTreeNode<T>) -> usize {
         // find index or insert new
         let index = match self.first_free_index {
@@ -106,7 +140,7 @@ impl<T: Hash> HashTree<T> {
         // fill slot
         let slot = self.values.get_mut(index).unwrap();
         let next_free = match slot {
-            Slot::Empty { next, .. } => next.clone(),
+            Slot::Empty { next, .. } => *next,
             _ => panic!("Slot is not empty"),
         };
         *slot = Slot::new_filled(node);
@@ -142,8 +176,8 @@ impl<T: Hash> HashTree<T> {
 
     pub fn get_root(&self) -> Option<&HashTreeNode<T>> {
         self.root_id
-            .map_or(None, |id| self.values.get(id))
-            .map_or(None, |s| match s {
+            .and_then(|id| self.values.get(id))
+            .and_then(|s| match s {
                 Slot::Filled { item } => Some(item),
                 _ => None,
             })
@@ -157,7 +191,7 @@ impl<T: Hash> HashTree<T> {
     }
 
     fn get_node_mut(&mut self, id: usize) -> Option<&mut HashTreeNode<T>> {
-        self.values.get_mut(id).map_or(None, |s| match s {
+        self.values.get_mut(id).and_then(|s| match s {
             Slot::Filled { item } => Some(item),
             _ => None,
         })
@@ -188,7 +222,7 @@ impl<T: Hash> HashTree<T> {
         let old_parent = node.parent;
         node.parent = Some(parent);
 
-        if let Some(parent_node) = old_parent.map_or(None, |id| self.get_node_mut(id)) {
+        if let Some(parent_node) = old_parent.and_then(|id| self.get_node_mut(id)) {
             parent_node.children.retain(|&child| child != id);
             self.update_hashes_of_branch(old_parent);
         }
@@ -198,9 +232,8 @@ impl<T: Hash> HashTree<T> {
     }
 
     pub fn remove_node(&mut self, id: usize) -> Result<HashTreeNode<T>, ()> {
-        match self.values[id] {
-            Slot::Empty { .. } => return Err(()),
-            _ => {}
+        if let Slot::Empty { .. } = self.values[id] {
+            return Err(());
         }
 
         let node = self.free_slot(id)?;
@@ -233,9 +266,8 @@ impl<T: Hash> HashTree<T> {
     }
 
     fn free_slot(&mut self, id: usize) -> Result<HashTreeNode<T>, ()> {
-        match self.values[id] {
-            Slot::Empty { .. } => return Err(()),
-            _ => {}
+        if let Slot::Empty { .. } = self.values[id] {
+            return Err(());
         }
 
         let mut next = None;
@@ -263,14 +295,12 @@ impl<T: Hash> HashTree<T> {
         let empty = Slot::new_empty(previous, next);
         self.values.push(empty);
 
-        match previous.map_or(None, |i| self.values.get_mut(i)) {
-            Some(Slot::Empty { next, .. }) => *next = Some(id),
-            _ => {}
+        if let Some(Slot::Empty { next, .. }) = previous.and_then(|i| self.values.get_mut(i)) {
+            *next = Some(id)
         }
 
-        match next.map_or(None, |i| self.values.get_mut(i)) {
-            Some(Slot::Empty { previous, .. }) => *previous = Some(id),
-            _ => {}
+        if let Some(Slot::Empty { previous, .. }) = next.and_then(|i| self.values.get_mut(i)) {
+            *previous = Some(id)
         }
 
         let slot = self.values.swap_remove(id);
@@ -324,6 +354,16 @@ impl<T: Hash> HashTree<T> {
         }
     }
 
+    /// Turns hashing back on after nodes were inserted without it, hashing only the given
+    /// nodes, which must be listed parents first. Subtrees copied by `insert_subtree` keep
+    /// the hashes they were copied with.
+    pub fn resume_hashes(&mut self, inserted: &[usize]) {
+        self.should_compute_hashes = true;
+        for id in inserted.iter().rev() {
+            self.refresh_hash_at(*id);
+        }
+    }
+
     pub fn to_vec(&self) -> Vec<(usize, &HashTreeNode<T>)> {
         self.values
             .iter()
@@ -357,7 +397,7 @@ impl<T: Hash> HashTree<T> {
     }
 }
 
-impl<T: Hash + Debug> HashTree<T> {
+impl<T: CanonicalEncode + Debug> HashTree<T> {
     #[rustfmt::skip]
     const INDENT_EMPTY   : &'static str = "    ";
     #[rustfmt::skip]
@@ -416,81 +456,82 @@ impl<T: Hash + Debug> HashTree<T> {
     }
 }
 
-impl<T: Hash + Debug + PartialEq> HashTree<T> {
+impl HashTree<Node> {
+    /// Computes the changes that turn this tree into another, comparing nodes by position
+    /// like [`TitTree::detect_changes`](crate::TitTree::detect_changes). Subtrees with equal
+    /// hashes are skipped without visiting them, so the cost depends on the size of the change.
     pub fn difference(&self, other: &Self) -> Vec<Change> {
-        let mut left = self.to_vec_with_paths();
-        let mut right = other.to_vec_with_paths();
+        // A missing root compares like the empty root of a default `TitTree`.
+        let empty = HashTreeNode::new(
+            Node {
+                kind: String::new(),
+                value: None,
+                role: None,
+            },
+            None,
+        );
+        let left = self.get_root().unwrap_or(&empty);
+        let right = other.get_root().unwrap_or(&empty);
+
         let mut difference = vec![];
+        self.difference_rec(
+            Some(left),
+            other,
+            Some(right),
+            &mut vec![0],
+            &mut difference,
+        );
+        difference
+    }
 
-        for (l_path, l_node) in left.drain(..) {
-            if let Some(matching) = right
-                .iter()
-                .position(|(r_path, r_node)| *r_path == l_path && **r_node == *l_node)
-            {
-                // no change
-                right.remove(matching);
-            } else if let Some(matching) = right.iter().position(|(_, r_node)| **r_node == *l_node)
-            {
-                let r_path = right[matching].0.clone();
-                difference.push(Change::Update(
-                    vec![],
-                    Node {
-                        kind: "file".to_string(),
-                        value: Some(
-                            r_path
-                                .iter()
-                                .map(|id| (*id).to_string())
-                                .collect::<String>(),
-                        ),
-                        role: Some("moved".to_string()),
-                    },
-                ));
-                right.remove(matching);
-            } else if let Some(matching) = right.iter().position(|(r_path, _)| *r_path == l_path) {
-                let r_path = right[matching].0.clone();
-                difference.push(Change::Update(
-                    vec![],
-                    Node {
-                        kind: "file".to_string(),
-                        value: Some(
-                            r_path
-                                .iter()
-                                .map(|id| (*id).to_string())
-                                .collect::<String>(),
-                        ),
-                        role: Some("changed".to_string()),
-                    },
-                ));
-                right.remove(matching);
-            } else {
-                difference.push(Change::Deletion(vec![]));
-            }
-        }
+    fn difference_rec(
+        &self,
+        left: Option<&HashTreeNode<Node>>,
+        other: &Self,
+        right: Option<&HashTreeNode<Node>>,
+        path: &mut Vec<usize>,
+        difference: &mut Vec<Change>,
+    ) {
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                if self.should_compute_hashes
+                    && other.should_compute_hashes
+                    && left.hash == right.hash
+                {
+                    return;
+                }
 
-        // anytihng left over in right is an addition
-        for (r_path, _) in right {
-            difference.push(Change::Addition(
-                vec![],
-                Node {
-                    kind: "file".to_string(),
-                    value: Some(
-                        r_path
-                            .iter()
-                            .map(|id| (*id).to_string())
-                            .collect::<String>(),
-                    ),
-                    role: Some("new".to_string()),
-                },
-            ));
-        }
+                // Inner nodes have values too, e.g. directories their name.
+                if left.value != right.value {
+                    difference.push(Change::Update(path.to_vec(), right.value.clone()));
+                }
 
-        difference
+                let len = left.children.len().max(right.children.len());
+                for index in 0..len {
+                    let left_child = left.children.get(index).and_then(|id| self.get_node(*id));
+                    let right_child = right.children.get(index).and_then(|id| other.get_node(*id));
+                    path.push(index);
+                    self.difference_rec(left_child, other, right_child, path, difference);
+                    path.pop();
+                }
+            }
+            (Some(_), None) => difference.push(Change::Deletion(path.to_vec())),
+            (None, Some(right)) => {
+                difference.push(Change::Addition(path.to_vec(), right.value.clone()));
+                for (index, child) in right.children.iter().enumerate() {
+                    path.push(index);
+                    self.difference_rec(None, other, other.get_node(*child), path, difference);
+                    path.pop();
+                }
+            }
+            (None, None) => {}
+        }
     }
 }
 
 impl<T> Debug for HashTree<T>
 where
-    T: Hash + Debug,
+    T: CanonicalEncode + Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let root_id = match self.root_id {
@@ -504,9 +545,17 @@ where
 
 #[cfg(test)]
 mod test {
-    use std::path::Path;
-    use crate::util::{BinaryFileRead, BinaryFileWrite};
     use super::HashTree;
+    use crate::util::{BinaryFileRead, BinaryFileWrite};
+    use crate::{Node, TitTree};
+
+    fn node(kind: &str) -> Node {
+        Node {
+            kind: kind.to_string(),
+            value: None,
+            role: None,
+        }
+    }
 
     #[test]
     #[allow(unused)]
@@ -534,12 +583,13 @@ mod test {
         // println!("{:?}", tree);
         println!("Saving...");
 
-        let path = Path::new("test.tree");
+        let dir = crate::testutil::temp_dir();
+        let path = dir.path().join("tree.bin");
 
-        tree.write_to(path);
+        tree.write_to(&path);
         println!("Loading...");
 
-        let tree2 = HashTree::<String>::read_from(path);
+        let tree2 = HashTree::<String>::read_from(&path);
 
         // println!("{:?}", tree2);
 
@@ -554,31 +604,30 @@ mod test {
 
         let mut random = 17;
 
-        let root_id = tree.insert_root("root");
+        let root_id = tree.insert_root(node("root"));
 
         for i in 0..1_000 {
             let parent = if i > 0 { random % i } else { 0 };
             random += 172742;
 
-            let child_id = tree.insert(parent, "child").unwrap();
+            let child_id = tree.insert(parent, node("child")).unwrap();
         }
         tree.refresh_hashes();
 
         let mut tree2 = HashTree::default();
         tree2.set_should_compute_hashes(false);
 
-        let root_id = tree2.insert_root("root");
+        let root_id = tree2.insert_root(node("root"));
         let mut random = 17;
 
         for i in 0..1_000 {
             let parent = if i > 0 { random % i } else { 0 };
             random += 172742;
 
-            let child_id = tree2.insert(parent, "child").unwrap();
+            let child_id = tree2.insert(parent, node("child")).unwrap();
         }
         tree2.refresh_hashes();
 
-
         let difference = tree.difference(&tree2);
 
         for d in difference.iter() {
@@ -587,7 +636,7 @@ mod test {
 
         assert_eq!(difference.len(), 0);
 
-        let child_id = tree2.insert(0, "child").unwrap();
+        let child_id = tree2.insert(0, node("child")).unwrap();
 
         let difference = tree.difference(&tree2);
 
@@ -597,4 +646,38 @@ mod test {
 
         assert_eq!(difference.len(), 1);
     }
+
+    #[test]
+    fn test_difference_matches_tit_tree() {
+        let mut before = HashTree::default();
+        let root = before.insert_root(node("root"));
+        let mut after = HashTree::default();
+        let after_root = after.insert_root(node("root"));
+
+        let mut random = 17;
+        for i in 0..200 {
+            let kind = if i % 7 == 0 { "leaf" } else { "child" };
+            before.insert(root + random % (i + 1), node(kind)).unwrap();
+            let kind = if i % 5 == 0 { "leaf" } else { "child" };
+            after
+                .insert(after_root + random % (i + 1), node(kind))
+                .unwrap();
+            random += 172742;
+        }
+        after.insert(after_root, node("new")).unwrap();
+
+        let expected = TitTree::from(&before).detect_changes(&TitTree::from(&after));
+        let difference = before.difference(&after);
+        assert!(!difference.is_empty());
+        assert_eq!(format!("{:?}", difference), format!("{:?}", expected));
+
+        assert!(before.difference(&before).is_empty());
+        assert_eq!(
+            format!("{:?}", HashTree::default().difference(&after)),
+            format!(
+                "{:?}",
+                TitTree::default().detect_changes(&TitTree::from(&after))
+            )
+        );
+    }
 }
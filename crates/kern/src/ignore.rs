@@ -15,8 +15,8 @@ pub fn get_ignorelist_of_dir(path: &Path) -> Vec<String> {
 }
 
 fn read_ignore_file(path: &Path) -> Vec<String> {
-    let contents =
-        fs::read_to_string(path).expect(&format!("Failed to read ignore file at {:?}", path));
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Failed to read ignore file at {:?}", path));
     contents
         .lines()
         .map(|l| l.trim().to_string())
@@ -1,31 +1,64 @@
+mod blame;
+mod blob;
 mod branch;
+mod cache;
 mod change;
 mod commit;
+mod config;
+mod content;
+mod encoding;
 mod error;
+mod filestatus;
+mod fsck;
+mod git;
 pub mod hashtree;
 mod ignore;
 mod node;
 mod path;
+mod query;
+mod rename;
 mod repository;
 mod repositorystate;
 mod repositorytree;
+mod signing;
+mod sparse;
+mod symbol;
+mod tag;
 pub mod terminal;
+#[cfg(test)]
+mod testutil;
 pub mod tree;
 pub mod util;
 
+pub use blame::*;
+pub use blob::*;
 pub use branch::*;
+pub use cache::*;
 pub use change::*;
 pub use commit::*;
+pub use config::*;
+pub use content::*;
+pub use encoding::*;
 pub use error::*;
+pub use filestatus::*;
+pub use fsck::*;
 pub use node::*;
 pub use path::*;
+pub use query::*;
+pub use rename::*;
 pub use repository::*;
 pub use repositorystate::*;
 pub use repositorytree::*;
+pub use signing::*;
+pub use sparse::*;
+pub use symbol::*;
+pub use tag::*;
 pub use tree::*;
 
+pub const BLOB_DIR: &str = "blobs";
 pub const BRANCH_DIR: &str = "branches";
 pub const COMMIT_DIR: &str = "commits";
+pub const TAG_DIR: &str = "tags";
 pub const DOT_GIT: &str = ".git";
 pub const DOT_TIT_IGNORE: &str = ".titignore";
 pub const DOT_TIT: &str = ".tit";
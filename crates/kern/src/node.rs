@@ -1,19 +1,18 @@
 use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+/// Role of a comment attached to the node that follows it.
+pub const ROLE_LEADING_COMMENT: &str = "leading_comment";
+/// Role of a comment attached to the node it trails on the same line.
+pub const ROLE_TRAILING_COMMENT: &str = "trailing_comment";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
 pub struct Node {
     pub kind: String,
     pub value: Option<String>,
     pub role: Option<String>,
 }
 
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.kind == other.kind && self.value == other.value && self.role == other.role
-    }
-}
-
 impl Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match (self.value.as_ref(), self.role.as_ref()) {
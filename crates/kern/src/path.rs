@@ -1 +1 @@
-pub type Path = Vec<usize>;
\ No newline at end of file
+pub type Path = Vec<usize>;
@@ -0,0 +1,260 @@
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use indextree::{Arena, NodeId};
+
+use crate::repositorytree::file_of;
+use crate::{Node, TitError, TitTree};
+
+/// Kind that matches nodes of any kind.
+const WILDCARD: &str = "_";
+
+/// A structural pattern over syntax trees, written as an S-expression in the style of
+/// tree-sitter queries:
+///
+/// ```text
+/// (call_expression function: (identifier "malloc"))
+/// (call_expression function: "malloc")
+/// (function_definition declarator: (_ declarator: "main"))
+/// ```
+///
+/// A pattern names the kind of a node (`_` for any kind) and optionally its value. Nested
+/// patterns must each match a different child, with the given role if one is written before
+/// them. A bare string matches a child of any kind with that value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    root: Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Pattern {
+    kind: Option<String>,
+    value: Option<String>,
+    role: Option<String>,
+    children: Vec<Pattern>,
+}
+
+impl Query {
+    /// Finds all nodes of the tree matching the query, together with the path of the file
+    /// each one lies in.
+    pub fn matches(&self, tree: &TitTree) -> Vec<(String, NodeId)> {
+        let arena = tree.arena();
+        tree.root_id()
+            .descendants(arena)
+            .filter(|node| self.root.matches(arena, *node))
+            .map(|node| (file_of(arena, node), node))
+            .collect()
+    }
+
+    pub fn matches_node(&self, tree: &TitTree, node: NodeId) -> bool {
+        self.root.matches(tree.arena(), node)
+    }
+}
+
+impl FromStr for Query {
+    type Err = TitError<'static>;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        let mut chars = query.chars().peekable();
+        let root = parse_pattern(&mut chars, None)?;
+
+        skip_whitespace(&mut chars);
+        match chars.peek() {
+            None => Ok(Query { root }),
+            Some(_) => Err(TitError("Unexpected input after query", None)),
+        }
+    }
+}
+
+impl Pattern {
+    fn matches(&self, arena: &Arena<Node>, node: NodeId) -> bool {
+        let value = arena[node].get();
+        if self.kind.as_ref().is_some_and(|kind| *kind != value.kind)
+            || self.value.as_ref().is_some_and(|v| value.value.as_ref() != Some(v))
+        {
+            return false;
+        }
+
+        let children: Vec<_> = node.children(arena).collect();
+        let mut used = vec![false; children.len()];
+        match_children(&self.children, arena, &children, &mut used)
+    }
+
+    fn matches_child(&self, arena: &Arena<Node>, child: NodeId) -> bool {
+        let role = &arena[child].get().role;
+        (self.role.is_none() || self.role == *role) && self.matches(arena, child)
+    }
+}
+
+/// Matches each pattern against a different child, trying the other children for earlier
+/// patterns when the later ones find none left.
+fn match_children(
+    patterns: &[Pattern],
+    arena: &Arena<Node>,
+    children: &[NodeId],
+    used: &mut [bool],
+) -> bool {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return true;
+    };
+
+    for (index, child) in children.iter().enumerate() {
+        if used[index] || !pattern.matches_child(arena, *child) {
+            continue;
+        }
+        used[index] = true;
+        if match_children(rest, arena, children, used) {
+            return true;
+        }
+        used[index] = false;
+    }
+    false
+}
+
+fn parse_pattern(
+    chars: &mut Peekable<Chars>,
+    role: Option<String>,
+) -> Result<Pattern, TitError<'static>> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('"') => {
+            let value = parse_string(chars)?;
+            return Ok(Pattern {
+                kind: None,
+                value: Some(value),
+                role,
+                children: vec![],
+            });
+        }
+        Some('(') => {
+            chars.next();
+        }
+        _ => return Err(TitError("Expected '(' or a string", None)),
+    }
+
+    skip_whitespace(chars);
+    let kind = parse_word(chars);
+    if kind.is_empty() {
+        return Err(TitError("Expected a node kind", None));
+    }
+
+    skip_whitespace(chars);
+    let value = match chars.peek() {
+        Some('"') => Some(parse_string(chars)?),
+        _ => None,
+    };
+
+    let mut children = vec![];
+    loop {
+        skip_whitespace(chars);
+        match chars.peek() {
+            Some(')') => {
+                chars.next();
+                break;
+            }
+            Some('(') | Some('"') => children.push(parse_pattern(chars, None)?),
+            Some(_) => {
+                let child_role = parse_word(chars);
+                skip_whitespace(chars);
+                if child_role.is_empty() || chars.next() != Some(':') {
+                    return Err(TitError("Expected a role followed by ':'", None));
+                }
+                children.push(parse_pattern(chars, Some(child_role))?);
+            }
+            None => return Err(TitError("Unterminated pattern, expected ')'", None)),
+        }
+    }
+
+    Ok(Pattern {
+        kind: (kind != WILDCARD).then_some(kind),
+        value,
+        role,
+        children,
+    })
+}
+
+fn parse_word(chars: &mut Peekable<Chars>) -> String {
+    let mut word = String::new();
+    while let Some(c) = chars.peek() {
+        if c.is_whitespace() || matches!(c, '(' | ')' | '"' | ':') {
+            break;
+        }
+        word.push(*c);
+        chars.next();
+    }
+    word
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, TitError<'static>> {
+    chars.next();
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some(c) => string.push(c),
+                None => break,
+            },
+            Some(c) => string.push(c),
+            None => break,
+        }
+    }
+    Err(TitError("Unterminated string", None))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::Query;
+    use crate::{Node, TitTree};
+    use indextree::Arena;
+
+    fn node(kind: &str, value: Option<&str>, role: Option<&str>) -> Node {
+        Node {
+            kind: kind.to_string(),
+            value: value.map(str::to_string),
+            role: role.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query: Query = r#"(call_expression function: (identifier "malloc") (_))"#
+            .parse()
+            .unwrap();
+        let root = &query.root;
+        assert_eq!(root.kind.as_deref(), Some("call_expression"));
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].role.as_deref(), Some("function"));
+        assert_eq!(root.children[0].value.as_deref(), Some("malloc"));
+        assert_eq!(root.children[1].kind, None);
+
+        assert!("(call_expression".parse::<Query>().is_err());
+        assert!("(call_expression function (_))".parse::<Query>().is_err());
+        assert!(r#"(identifier "x)"#.parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_child_patterns_match_different_children() {
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("binary_expression", None, None));
+        for (kind, value, role) in [
+            ("identifier", "a", "left"),
+            ("+", "+", "operator"),
+            ("number_literal", "1", "right"),
+        ] {
+            let child = arena.new_node(node(kind, Some(value), Some(role)));
+            root.append(child, &mut arena);
+        }
+        let tree = TitTree::new(arena, root);
+
+        let matches = |query: &str| query.parse::<Query>().unwrap().matches_node(&tree, root);
+        assert!(matches("(binary_expression (identifier) (_))"));
+        assert!(matches("(binary_expression (_) (identifier))"));
+        assert!(!matches("(binary_expression (identifier) (identifier))"));
+        assert!(!matches("(binary_expression (_) (_) (_) (_))"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use indextree::{Arena, NodeId};
+
+use crate::repositorytree::KIND_FILE;
+use crate::{Change, Node, Path, TitTree};
+
+/// Role of the child that holds the name a declaration introduces.
+pub(crate) const ROLE_DECLARATOR: &str = "declarator";
+
+/// A symbol renamed together with all of its uses within the scope it is declared in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    /// Name of the declaration or file the renamed symbol is declared in.
+    pub scope: String,
+    /// Paths of the updated declaration and uses.
+    pub paths: Vec<Path>,
+}
+
+impl Rename {
+    pub fn covers(&self, change: &Change) -> bool {
+        matches!(change, Change::Update(..)) && self.paths.contains(change.path())
+    }
+}
+
+impl Display for Rename {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rename symbol {} -> {} in scope {} ({} occurrences)",
+            self.from,
+            self.to,
+            self.scope,
+            self.paths.len()
+        )
+    }
+}
+
+/// Groups the updates of identifiers that rename a declared symbol into one rename each.
+///
+/// A rename is only reported if the declaration itself, a node with the `declarator`
+/// role, was updated. Uses of the old name are grouped if they lie in the scope
+/// of that declaration.
+pub fn summarize_renames(before: &TitTree, changes: &[Change]) -> Vec<Rename> {
+    let arena = before.arena();
+
+    // (from, to) -> updated nodes
+    let mut groups: BTreeMap<(String, String), Vec<(&Path, NodeId)>> = BTreeMap::new();
+    for change in changes {
+        let (path, new_node) = match change {
+            Change::Update(path, new_node) => (path, new_node),
+            _ => continue,
+        };
+        let old_id = match before.node_id_at(path) {
+            Some(id) => id,
+            None => continue,
+        };
+        let old_node = arena[old_id].get();
+
+        if let (Some(from), Some(to)) = (&old_node.value, &new_node.value) {
+            if old_id.children(arena).next().is_none()
+                && old_node.kind == new_node.kind
+                && old_node.role == new_node.role
+                && from != to
+            {
+                groups
+                    .entry((from.clone(), to.clone()))
+                    .or_default()
+                    .push((path, old_id));
+            }
+        }
+    }
+
+    let mut renames = vec![];
+    for ((from, to), updates) in groups {
+        let declarations = updates
+            .iter()
+            .filter(|(_, id)| arena[*id].get().role.as_deref() == Some(ROLE_DECLARATOR));
+
+        for (declaration_path, declaration_id) in declarations {
+            let (scope, scope_id) = declaring_scope(arena, *declaration_id);
+            let scope_depth = scope_id.ancestors(arena).count();
+            let scope_path = &declaration_path[..scope_depth];
+
+            let paths = updates
+                .iter()
+                .filter(|(path, _)| path.starts_with(scope_path))
+                .map(|(path, _)| path.to_vec())
+                .filter(|path| !renames.iter().any(|r: &Rename| r.paths.contains(path)))
+                .collect();
+
+            renames.push(Rename {
+                from: from.clone(),
+                to: to.clone(),
+                scope,
+                paths,
+            });
+        }
+    }
+
+    renames
+}
+
+/// Finds the nearest enclosing declaration of another name, or else the file or root,
+/// that a declaration lies in. Returns its name and node.
+fn declaring_scope(arena: &Arena<Node>, declaration: NodeId) -> (String, NodeId) {
+    let mut root = declaration;
+    for ancestor in declaration.ancestors(arena).skip(1) {
+        root = ancestor;
+        let node = arena[ancestor].get();
+        if node.kind == KIND_FILE {
+            return (node.value.clone().unwrap_or_default(), ancestor);
+        }
+
+        if let Some(name_id) = declared_name(arena, ancestor) {
+            if name_id != declaration {
+                // A function declarator names the function, but its scope is the
+                // whole function definition around it.
+                let mut scope = ancestor;
+                while let Some(parent) = arena[scope].parent() {
+                    if declared_name(arena, parent) != Some(name_id) {
+                        break;
+                    }
+                    scope = parent;
+                }
+
+                let name = arena[name_id].get().value.clone().unwrap_or_default();
+                return (name, scope);
+            }
+        }
+    }
+
+    let node = arena[root].get();
+    (node.value.clone().unwrap_or(node.kind.clone()), root)
+}
+
+/// Follows the `declarator` roles below a node down to the name it declares.
+pub(crate) fn declared_name(arena: &Arena<Node>, node: NodeId) -> Option<NodeId> {
+    let declarator = node
+        .children(arena)
+        .find(|child| arena[*child].get().role.as_deref() == Some(ROLE_DECLARATOR))?;
+
+    match declared_name(arena, declarator) {
+        Some(name) => Some(name),
+        None if arena[declarator].get().value.is_some() => Some(declarator),
+        None => None,
+    }
+}
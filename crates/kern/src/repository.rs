@@ -1,15 +1,30 @@
 use crate::hashtree::HashTree;
+use crate::symbol::{symbol_changes, symbol_subtrees};
 use crate::terminal::CheckList;
 use crate::util::{BinaryFileRead, BinaryFileWrite, TomlFileRead, TomlFileWrite};
-use crate::{build_hash_tree_for_dir, util, InitError, Node, DOT_TIT};
-use crate::{Commit, RepositoryState};
-use std::collections::HashMap;
+use crate::{
+    build_hash_tree_with_cache, util, write_hash_tree_to_dir, ContentFormat, InitError, Node,
+    TitTree, TreeCache, DOT_TIT, NO_COMMIT,
+};
+use crate::{Blame, Commit, Identity, RepositoryState, SymbolChange, Tag, TitError, UserConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct TitRepository {
     root: PathBuf,
+    content_format: Option<Arc<dyn ContentFormat>>,
+}
+
+impl fmt::Debug for TitRepository {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TitRepository")
+            .field("root", &self.root)
+            .finish()
+    }
 }
 
 impl Default for TitRepository {
@@ -23,7 +38,20 @@ impl Default for TitRepository {
 
 impl TitRepository {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            content_format: None,
+        }
+    }
+
+    /// Tracks the contents of files the given format can parse as syntax trees.
+    pub fn with_content_format(mut self, content_format: Arc<dyn ContentFormat>) -> Self {
+        self.content_format = Some(content_format);
+        self
+    }
+
+    pub fn content_format(&self) -> Option<&dyn ContentFormat> {
+        self.content_format.as_deref()
     }
 
     pub fn init(&self, name: &str, server: &str, branch: &str) -> Result<(), InitError> {
@@ -46,6 +74,8 @@ impl TitRepository {
         fs::create_dir(&dot_tit_dir).map_err(|_| InitError::DirectoryCreateError(dot_tit_dir))?;
         let commits_dir = root.join(self.commits_dir());
         fs::create_dir(&commits_dir).map_err(|_| InitError::DirectoryCreateError(commits_dir))?;
+        let tags_dir = self.tags_dir();
+        fs::create_dir(&tags_dir).map_err(|_| InitError::DirectoryCreateError(tags_dir))?;
         checklist.finish_step();
 
         // create state file
@@ -73,10 +103,23 @@ impl TitRepository {
         }
     }
 
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the repository's own files, also used for scratch space.
+    pub(crate) fn dot_tit_dir(&self) -> PathBuf {
+        self.root.join(DOT_TIT)
+    }
+
     fn commits_dir(&self) -> PathBuf {
         self.root.join(crate::DOT_TIT).join(crate::COMMIT_DIR)
     }
 
+    fn tags_dir(&self) -> PathBuf {
+        self.root.join(crate::DOT_TIT).join(crate::TAG_DIR)
+    }
+
     fn commit_file(&self, commit_id: &str) -> PathBuf {
         self.commits_dir().join(commit_id)
     }
@@ -89,14 +132,58 @@ impl TitRepository {
         self.root.join(crate::DOT_TIT).join("tree.bin")
     }
 
-    pub fn write_commit(&self, commit: &Commit) {
-        let commit_path = self.commit_file(&commit.get_id());
-        commit.write_to(&commit_path);
+    fn cache_file(&self) -> PathBuf {
+        self.root.join(crate::DOT_TIT).join("cache.bin")
+    }
+
+    /// Stores a commit under its ID, which is returned. Fails for commits of versions this
+    /// build does not know.
+    pub fn write_commit(&self, commit: &Commit) -> Result<String, TitError<'static>> {
+        let id = commit.get_id()?;
+        commit.write_to(self.commit_file(&id));
+        Ok(id)
     }
 
     pub fn read_commit(&self, id: &str) -> Commit {
-        let commit_path = self.commit_file(id);
-        Commit::read_from(&commit_path)
+        self.try_read_commit(id).expect("Failed to read commit")
+    }
+
+    /// Reads a commit, failing instead of panicking if it is missing or damaged. Commits
+    /// written in the layouts of earlier versions are migrated, see [`Commit::decode`].
+    pub fn try_read_commit(&self, id: &str) -> Result<Commit, TitError<'static>> {
+        let bytes = fs::read(self.commit_file(id))
+            .map_err(|e| TitError("Failed to read commit", Some(e)))?;
+        Commit::decode(&bytes, id)
+    }
+
+    /// Stores a tag, replacing any tag of the same name.
+    pub fn write_tag(&self, tag: &Tag) {
+        let tags_dir = self.tags_dir();
+        fs::create_dir_all(&tags_dir).expect("Failed to create tags directory!");
+        tag.write_to(tags_dir.join(&tag.name));
+    }
+
+    pub fn read_tag(&self, name: &str) -> Option<Tag> {
+        let tag_path = self.tags_dir().join(name);
+        match Tag::is_valid_name(name) && tag_path.exists() {
+            true => Some(Tag::read_from(tag_path)),
+            false => None,
+        }
+    }
+
+    pub fn tags(&self) -> BTreeMap<String, Tag> {
+        let tags_dir = self.tags_dir();
+        if !tags_dir.exists() {
+            return BTreeMap::new();
+        }
+
+        fs::read_dir(tags_dir)
+            .expect("Failed to read tags directory!")
+            .map(|entry| {
+                let tag = Tag::read_from(entry.expect("Failed to read entry.").path());
+                (tag.name.clone(), tag)
+            })
+            .collect()
     }
 
     pub fn commit_ids(&self) -> Vec<String> {
@@ -120,23 +207,317 @@ impl TitRepository {
             .collect::<_>()
     }
 
+    /// Finds the full id of a commit from an unambiguous prefix of it.
+    pub fn resolve_commit(&self, prefix: &str) -> Option<String> {
+        let mut matching = self
+            .commit_ids()
+            .into_iter()
+            .filter(|id| id.starts_with(prefix));
+        match (matching.next(), matching.next()) {
+            (Some(id), None) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Returns the ids of the given commit and all of its predecessors, newest first.
+    pub fn history(&self, commit_id: &str) -> Vec<String> {
+        let mut history = vec![];
+        let mut current = Some(commit_id.to_string());
+
+        while let Some(id) = current {
+            if !self.commit_file(&id).exists() {
+                break;
+            }
+            current = self.read_commit(&id).predecessor_id;
+            history.push(id);
+        }
+
+        history
+    }
+
+    /// Rebuilds the directory tree of a commit by replaying its history.
+    pub fn tree_at(&self, commit_id: &str) -> HashTree<Node> {
+        let mut tree = TitTree::default();
+        for id in self.history(commit_id).iter().rev() {
+            tree.apply_changes(&self.read_commit(id).changes);
+        }
+        HashTree::from(&tree)
+    }
+
+    /// Replays the history of a commit, recording which commit last changed each node.
+    pub fn blame(&self, commit_id: &str) -> Blame {
+        let mut blame = Blame::default();
+        for id in self.history(commit_id).iter().rev() {
+            blame.apply_commit(id, &self.read_commit(id).changes);
+        }
+        blame
+    }
+
+    /// Lists the changes to the definitions of a function or type named `symbol` made by the
+    /// given commit and its predecessors, newest first.
+    pub fn symbol_history(&self, commit_id: &str, symbol: &str) -> Vec<SymbolChange> {
+        let mut tree = TitTree::default();
+        let mut history = vec![];
+
+        for id in self.history(commit_id).iter().rev() {
+            let before = symbol_subtrees(&tree, symbol);
+            tree.apply_changes(&self.read_commit(id).changes);
+            let after = symbol_subtrees(&tree, symbol);
+            history.extend(symbol_changes(id, &before, &after));
+        }
+
+        history.reverse();
+        history
+    }
+
+    /// Materializes the tree of a commit in the working directory and signs it. In a sparse
+    /// checkout, only the selected subtrees are written.
+    pub fn checkout(&self, commit_id: &str) {
+        let tree = self.tree_at(commit_id);
+        let sparse = self.state().sparse_selection();
+        write_hash_tree_to_dir(
+            &tree,
+            &self.root,
+            self.content_format(),
+            &sparse,
+            &self.blob_store(),
+        );
+        // The scan takes what is not checked out from the signed tree.
+        self.set_signed_tree(tree);
+        self.set_signed_tree(self.current_tree());
+    }
+
     pub fn state(&self) -> RepositoryState {
         RepositoryState::read_from(&self.state_file())
     }
 
+    /// Who commits in this repository, as set in the repository state or else in the user
+    /// config.
+    pub fn identity(&self) -> Option<Identity> {
+        self.state().user.or_else(|| UserConfig::load().user)
+    }
+
     pub fn set_state(&self, state: RepositoryState) {
         state.write_to(&self.state_file())
     }
 
+    /// The tree of the head as it was last committed or checked out. A tree file written
+    /// by an earlier version, with hashes of another size, is replaced by the tree of the
+    /// head rebuilt from the history.
     pub fn signed_tree(&self) -> HashTree<Node> {
-        HashTree::<_>::read_from(&self.tree_file())
+        if let Some(tree) = HashTree::<_>::try_read_from(&self.tree_file()) {
+            return tree;
+        }
+
+        let tree = match self.state().current_commit_id() {
+            Some(id) if id != NO_COMMIT => self.tree_at(id),
+            _ => HashTree::default(),
+        };
+        tree.write_to(self.tree_file());
+        tree
     }
 
+    /// Scans the working directory. Files that did not change since the last scan keep the
+    /// syntax tree they have in the signed tree instead of being parsed again. In a sparse
+    /// checkout, everything outside the selection is taken from the signed tree.
+    ///
+    /// The contents of files are only hashed, see [`TitRepository::store_blobs`].
     pub fn current_tree(&self) -> HashTree<Node> {
-        build_hash_tree_for_dir(self.root.as_path())
+        let sparse = self.state().sparse_selection();
+
+        // A cache written by an earlier version is dropped, so every file is scanned again.
+        let cache_path = self.cache_file();
+        let mut cache = TreeCache::try_read_from(&cache_path).unwrap_or_default();
+        let previous = self.signed_tree();
+
+        let tree = build_hash_tree_with_cache(
+            &self.root,
+            self.content_format(),
+            &previous,
+            &mut cache,
+            &sparse,
+            None,
+        );
+        cache.write_to(&cache_path);
+        tree
     }
 
     pub fn set_signed_tree(&self, after: HashTree<Node>) {
         after.write_to(self.tree_file());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use serde::Serialize;
+
+    use crate::filestatus::collect_files;
+    use crate::hashtree::{HashTree, HashTreeNode};
+    use crate::testutil::{init_repository, temp_dir, write_file};
+    use crate::util::{to_serialized_bytes, BinaryFileRead};
+    use crate::{detect_tree_changes, file_statuses, Commit, FileStamp, Node, PersonStamp};
+    use crate::{TitTree, TreeCache, EMPTY_HASH};
+
+    /// The layouts of the tree and cache files before node hashes were SHA3-256 digests.
+    #[derive(Serialize)]
+    struct HashTreeV0 {
+        values: Vec<SlotV0>,
+        root_id: Option<usize>,
+        first_free_index: Option<usize>,
+        should_compute_hashes: bool,
+    }
+
+    #[derive(Serialize)]
+    enum SlotV0 {
+        #[allow(dead_code)]
+        Empty {
+            previous: Option<usize>,
+            next: Option<usize>,
+        },
+        Filled {
+            item: HashTreeNodeV0,
+        },
+    }
+
+    #[derive(Serialize)]
+    struct HashTreeNodeV0 {
+        value: Node,
+        hash: u64,
+        parent: Option<usize>,
+        children: Vec<usize>,
+    }
+
+    #[derive(Serialize)]
+    struct TreeCacheV0 {
+        files: HashMap<String, CachedFileV0>,
+    }
+
+    #[derive(Serialize)]
+    struct CachedFileV0 {
+        stamp: FileStamp,
+        hash: u64,
+    }
+
+    /// Adds a node and its descendants to `values`, numbered in the order they are visited.
+    fn add_v0(
+        tree: &HashTree<Node>,
+        node: &HashTreeNode<Node>,
+        parent: Option<usize>,
+        values: &mut Vec<SlotV0>,
+    ) {
+        let index = values.len();
+        values.push(SlotV0::Filled {
+            item: HashTreeNodeV0 {
+                value: node.value.clone(),
+                hash: 42,
+                parent,
+                children: vec![],
+            },
+        });
+        for child in &node.children {
+            let child_index = values.len();
+            add_v0(tree, tree.get_node(*child).unwrap(), Some(index), values);
+            if let SlotV0::Filled { item } = &mut values[index] {
+                item.children.push(child_index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_repository_in_old_format_is_rebuilt() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        write_file(dir, "a.txt", "a");
+        write_file(dir, "src/b.txt", "b");
+        let repository = init_repository(dir);
+
+        let tree = repository.current_tree();
+        let signature = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 0,
+            offset: 0,
+        };
+        let commit = Commit::new(
+            "first".to_string(),
+            detect_tree_changes(&HashTree::default(), &tree),
+            None,
+            tree.get_root().map(|root| root.hash).unwrap_or(EMPTY_HASH),
+            signature.clone(),
+            signature,
+        );
+        let id = repository.write_commit(&commit).unwrap();
+        let mut state = repository.state();
+        state
+            .branches
+            .insert(state.current.branch.clone(), id.clone());
+        repository.set_state(state);
+
+        let mut values = vec![];
+        add_v0(&tree, tree.get_root().unwrap(), None, &mut values);
+        let old_tree = HashTreeV0 {
+            values,
+            root_id: Some(0),
+            first_free_index: None,
+            should_compute_hashes: true,
+        };
+        fs::write(
+            repository.tree_file(),
+            to_serialized_bytes(&old_tree).unwrap(),
+        )
+        .unwrap();
+        let old_cache = TreeCacheV0 {
+            files: HashMap::from([(
+                "a.txt".to_string(),
+                CachedFileV0 {
+                    stamp: FileStamp::from(&fs::metadata(dir.join("a.txt")).unwrap()),
+                    hash: 42,
+                },
+            )]),
+        };
+        fs::write(
+            repository.cache_file(),
+            to_serialized_bytes(&old_cache).unwrap(),
+        )
+        .unwrap();
+
+        // The signed tree is the tree of the head again, and stored in the current layout.
+        let signed = repository.signed_tree();
+        assert_eq!(
+            TitTree::from(&signed),
+            TitTree::from(&repository.tree_at(&id))
+        );
+        assert_eq!(
+            signed.get_root().unwrap().hash,
+            repository.signed_tree().get_root().unwrap().hash
+        );
+        assert!(file_statuses(&signed, &repository.current_tree()).is_empty());
+    }
+
+    #[test]
+    fn test_cache_is_kept_without_content_format() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        let repository = init_repository(dir);
+        assert!(repository.content_format().is_none());
+        write_file(dir, "a.txt", "a\n");
+        write_file(dir, "docs/b.txt", "b\n");
+
+        let tree = repository.current_tree();
+        let cache = TreeCache::try_read_from(&repository.cache_file()).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // Every file is cached with the hash of its node, so unchanged files are reused.
+        let files = collect_files(&tree);
+        assert_eq!(files.len(), 2);
+        for (path, node) in files {
+            assert_eq!(cache.get(&path).map(|file| file.hash), Some(node.hash));
+        }
+
+        repository.set_signed_tree(tree);
+        assert!(file_statuses(&repository.signed_tree(), &repository.current_tree()).is_empty());
+    }
+}
@@ -1,6 +1,16 @@
 use crate::util::TomlFile;
+use crate::{Identity, SparseSelection};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Commit id stored for branches that do not point to a commit yet.
+pub const NO_COMMIT: &str = "none";
+
+/// Name of the server a repository is initialized or cloned with.
+pub const DEFAULT_SERVER: &str = "default";
+
+/// Stands for every branch in branch settings.
+pub const ALL_BRANCHES: &str = "*";
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Project {
@@ -19,6 +29,23 @@ pub struct RepositoryState {
     pub current: Current,
     pub branches: BTreeMap<String, String>,
     pub servers: BTreeMap<String, String>,
+    /// Branch heads of each server as seen by the last fetch, keyed by server name.
+    #[serde(default)]
+    pub remote_branches: BTreeMap<String, BTreeMap<String, String>>,
+    /// Identity used for commits in this repository instead of the one of the user config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<Identity>,
+    /// Branches that only accept commits signed by trusted keys when pushed to a server, or
+    /// `*` for all.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub signed_branches: BTreeSet<String>,
+    /// Public keys, as hex, whose signatures are trusted on a branch, keyed by branch name or
+    /// `*` for all.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trusted_keys: BTreeMap<String, BTreeSet<String>>,
+    /// Paths of the subtrees checked out in a sparse checkout, empty for the whole tree.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sparse: Vec<String>,
 }
 
 impl TomlFile for RepositoryState {}
@@ -27,11 +54,11 @@ impl RepositoryState {
     pub fn new(project_name: String, current_branch: String, current_server: String) -> Self {
         // prepare branches
         let mut branches = BTreeMap::new();
-        branches.insert(current_branch.to_string(), "none".to_string());
+        branches.insert(current_branch.to_string(), NO_COMMIT.to_string());
 
         // prepare servers
         let mut servers = BTreeMap::new();
-        servers.insert("default".to_string(), current_server.clone());
+        servers.insert(DEFAULT_SERVER.to_string(), current_server.clone());
 
         // prepare project
         let project = Project { name: project_name };
@@ -55,6 +82,74 @@ impl RepositoryState {
             current,
             branches,
             servers,
+            remote_branches: BTreeMap::new(),
+            user: None,
+            signed_branches: BTreeSet::new(),
+            trusted_keys: BTreeMap::new(),
+            sparse: vec![],
         }
     }
+
+    pub fn remote_commit_id(&self, server: &str, branch: &str) -> Option<&String> {
+        self.remote_branches
+            .get(server)
+            .and_then(|branches| branches.get(branch))
+            .filter(|id| id.as_str() != NO_COMMIT)
+    }
+
+    pub fn requires_signatures(&self, branch: &str) -> bool {
+        self.signed_branches.contains(branch) || self.signed_branches.contains(ALL_BRANCHES)
+    }
+
+    /// Whether signatures by the public key are trusted on the branch.
+    pub fn trusts_key(&self, branch: &str, key: &str) -> bool {
+        [branch, ALL_BRANCHES]
+            .iter()
+            .filter_map(|branch| self.trusted_keys.get(*branch))
+            .any(|keys| keys.contains(key))
+    }
+
+    /// Whether signatures by the public key are trusted on any branch.
+    pub fn trusts_key_anywhere(&self, key: &str) -> bool {
+        self.trusted_keys.values().any(|keys| keys.contains(key))
+    }
+
+    pub fn sparse_selection(&self) -> SparseSelection {
+        SparseSelection::new(&self.sparse)
+    }
+
+    pub fn current_commit_id(&self) -> Option<&String> {
+        self.branches
+            .get(&self.current.branch)
+            .filter(|id| id.as_str() != NO_COMMIT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RepositoryState, ALL_BRANCHES};
+
+    #[test]
+    fn test_trusted_keys() {
+        let mut state =
+            RepositoryState::new("test".to_string(), "main".to_string(), "none".to_string());
+        state
+            .trusted_keys
+            .entry("main".to_string())
+            .or_default()
+            .insert("aa".to_string());
+        state
+            .trusted_keys
+            .entry(ALL_BRANCHES.to_string())
+            .or_default()
+            .insert("bb".to_string());
+
+        assert!(state.trusts_key("main", "aa"));
+        assert!(state.trusts_key("main", "bb"));
+        assert!(!state.trusts_key("dev", "aa"));
+        assert!(state.trusts_key("dev", "bb"));
+        assert!(!state.trusts_key("main", "cc"));
+        assert!(state.trusts_key_anywhere("aa"));
+        assert!(!state.trusts_key_anywhere("cc"));
+    }
 }
@@ -1,58 +1,620 @@
-use crate::{hashtree::HashTree, ignore::get_ignorelist_of_dir, Node};
-use std::path::Path;
+use crate::filestatus::collect_files;
+use crate::hashtree::{HashTree, HashTreeNode};
+use crate::{ignore::get_ignorelist_of_dir, Change, ContentFormat, Node, TitRepository, TitTree};
+use crate::{BlobRef, BlobStore, CachedFile, FileStamp, SparseSelection, TreeCache};
+use crate::{KIND_BLOB, ROLE_SOURCE};
+use indextree::{Arena, NodeId};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::{fs, io, path::Path};
 
-const KIND_DIR: &str = "dir";
-const KIND_FILE: &str = "file";
+pub(crate) const KIND_DIR: &str = "dir";
+pub(crate) const KIND_FILE: &str = "file";
 
-pub fn build_hash_tree_for_dir(root_dir: &Path) -> HashTree<Node> {
+/// Builds the tree of a directory. Files the content format can parse get their syntax
+/// tree as the first child of their node, followed by a blob node with the hash and size of
+/// their source, all others a blob node with the hash and size of their content.
+pub fn build_hash_tree_for_dir(
+    root_dir: &Path,
+    format: Option<&dyn ContentFormat>,
+) -> HashTree<Node> {
+    build_hash_tree_with_cache(
+        root_dir,
+        format,
+        &HashTree::default(),
+        &mut TreeCache::default(),
+        &SparseSelection::default(),
+        None,
+    )
+}
+
+/// Builds the tree of a directory like [`build_hash_tree_for_dir`], but takes the nodes of
+/// files that did not change since the last scan from `previous` instead of parsing them
+/// again. The cache is replaced by the files found in this scan.
+///
+/// Only the entries of the sparse selection are scanned. All others are taken from
+/// `previous`, at the position they have there.
+///
+/// The contents of all files, and so the sources of parsed ones, are stored in `blobs`, if
+/// given.
+pub fn build_hash_tree_with_cache(
+    root_dir: &Path,
+    format: Option<&dyn ContentFormat>,
+    previous: &HashTree<Node>,
+    cache: &mut TreeCache,
+    sparse: &SparseSelection,
+    blobs: Option<&BlobStore>,
+) -> HashTree<Node> {
+    let scan = Scan {
+        format,
+        previous_files: collect_files(previous),
+        cache,
+        sparse,
+        blobs,
+    };
+    let scanned = match scan_fs_entry(root_dir, "", &scan) {
+        Some(scanned) => scanned,
+        None => return HashTree::default(),
+    };
+
+    // Merging in the order of the scan keeps child order and hashes independent of the
+    // order in which the threads finished. Every insert would hash its whole branch again,
+    // so the inserted nodes are hashed once at the end instead.
     let mut tree = HashTree::default();
-    scan_and_add_fs_entry(&mut tree, None, root_dir);
+    tree.set_should_compute_hashes(false);
+    let mut merge = Merge {
+        previous,
+        sparse,
+        inserted: Vec::new(),
+        files: Vec::new(),
+    };
+    add_scanned_entry(&mut tree, None, scanned, previous.get_root(), &mut merge);
+    tree.resume_hashes(&merge.inserted);
+
+    *cache = TreeCache::default();
+    for (relative, stamp, file_id) in merge.files {
+        let hash = tree.get_node(file_id).expect("File should exist").hash;
+        cache.insert(relative, CachedFile { stamp, hash });
+    }
+
     tree
 }
 
-fn scan_and_add_fs_entry(arena: &mut HashTree<Node>, parent: Option<usize>, path: &Path) {
+/// Computes the structural changes that turn one directory tree into another.
+pub fn detect_tree_changes(before: &HashTree<Node>, after: &HashTree<Node>) -> Vec<Change> {
+    before.difference(after)
+}
+
+/// Rewrites the syntax trees of all files into their canonical form, see
+/// [`ContentFormat::normalize`].
+pub fn normalize_hash_tree(tree: &HashTree<Node>, format: &dyn ContentFormat) -> HashTree<Node> {
+    let mut normalized = HashTree::default();
+    if let Some(root) = tree.get_root() {
+        normalized.set_should_compute_hashes(false);
+        let normalized_root = normalized.insert_root(root.value.clone());
+        let root_dir = Path::new(root.value.value.as_deref().unwrap_or_default());
+        normalize_fs_entries(
+            tree,
+            root,
+            &mut normalized,
+            normalized_root,
+            root_dir,
+            format,
+        );
+        normalized.set_should_compute_hashes(true);
+    }
+    normalized
+}
+
+fn normalize_fs_entries(
+    tree: &HashTree<Node>,
+    node: &HashTreeNode<Node>,
+    normalized: &mut HashTree<Node>,
+    normalized_node: usize,
+    dir: &Path,
+    format: &dyn ContentFormat,
+) {
+    for child_id in &node.children {
+        let child = tree.get_node(*child_id).expect("Child node should exist");
+        let path = dir.join(child.value.value.as_deref().unwrap_or_default());
+        let normalized_child = normalized
+            .insert(normalized_node, child.value.clone())
+            .expect("Failed to insert node");
+
+        if child.value.kind == KIND_DIR {
+            normalize_fs_entries(tree, child, normalized, normalized_child, &path, format);
+        } else if let Some(content) = file_content(tree, child) {
+            let mut content = TitTree::from_hash_node(tree, content);
+            format.normalize(&path, &mut content);
+            content.insert_into(normalized, normalized_child);
+        } else if let Some(blob) = file_blob(tree, child) {
+            normalized
+                .insert(normalized_child, blob.to_node())
+                .expect("Failed to insert node");
+        }
+    }
+}
+
+/// Creates the directories and files of a tree below the given directory, as far as they
+/// are in the sparse selection.
+///
+/// Files with a syntax tree are rendered by the content format, keeping the formatting
+/// of their committed source, or of what is already on disk while the source is not
+/// downloaded. Files with a blob get the blob's content from the store, and are left alone
+/// if it is not downloaded yet. Other files are created empty if they do not exist.
+pub fn write_hash_tree_to_dir(
+    tree: &HashTree<Node>,
+    root_dir: &Path,
+    format: Option<&dyn ContentFormat>,
+    sparse: &SparseSelection,
+    blobs: &BlobStore,
+) {
+    if let Some(root) = tree.get_root() {
+        write_fs_entries(tree, &root.children, root_dir, "", format, sparse, blobs);
+    }
+}
+
+fn write_fs_entries(
+    tree: &HashTree<Node>,
+    children: &[usize],
+    dir: &Path,
+    relative: &str,
+    format: Option<&dyn ContentFormat>,
+    sparse: &SparseSelection,
+    blobs: &BlobStore,
+) {
+    for child_id in children {
+        let child = tree.get_node(*child_id).expect("Child node should exist");
+        let name = child
+            .value
+            .value
+            .as_ref()
+            .expect("Entry should have a name");
+        let path = dir.join(name);
+        let child_relative = join_relative(relative, name);
+        if !sparse.includes(&child_relative) {
+            continue;
+        }
+
+        if child.value.kind == KIND_DIR {
+            fs::create_dir_all(&path).expect("Failed to create directory!");
+            write_fs_entries(
+                tree,
+                &child.children,
+                &path,
+                &child_relative,
+                format,
+                sparse,
+                blobs,
+            );
+            continue;
+        }
+
+        if let Some(blob) = file_blob(tree, child) {
+            let unchanged = fs::metadata(&path).is_ok_and(|metadata| metadata.len() == blob.size)
+                && BlobRef::of_file(&path, None).ok() == Some(blob);
+            if !unchanged && blobs.missing_objects(&blob).is_empty() {
+                blobs
+                    .read_to_file(&blob, &path)
+                    .expect("Failed to write file!");
+            }
+            continue;
+        }
+
+        let current = fs::read_to_string(&path).ok();
+        let source = file_source(tree, child).and_then(|source| read_source(blobs, &source, &path));
+        let rendered = format
+            .zip(file_content(tree, child))
+            .and_then(|(format, content)| {
+                let content = TitTree::from_hash_node(tree, content);
+                format.render(&path, &content, source.as_deref().or(current.as_deref()))
+            });
+
+        match rendered {
+            Some(rendered) if current.as_ref() != Some(&rendered) => {
+                fs::write(&path, rendered).expect("Failed to write file!");
+            }
+            None if !path.exists() => {
+                fs::File::create(&path).expect("Failed to create file!");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Joins the names of the directories and the file a node lies in, below the root.
+pub(crate) fn file_of(arena: &Arena<Node>, node: NodeId) -> String {
+    let mut names: Vec<_> = node
+        .ancestors(arena)
+        .filter(|ancestor| arena[*ancestor].parent().is_some())
+        .map(|ancestor| arena[ancestor].get())
+        .filter(|value| value.kind == KIND_DIR || value.kind == KIND_FILE)
+        .filter_map(|value| value.value.clone())
+        .collect();
+    names.reverse();
+    names.join("/")
+}
+
+/// Returns the root of a file's syntax tree, if its content was parsed.
+pub(crate) fn file_content<'a>(
+    tree: &'a HashTree<Node>,
+    file: &HashTreeNode<Node>,
+) -> Option<&'a HashTreeNode<Node>> {
+    file.children
+        .first()
+        .map(|id| tree.get_node(*id).expect("Content node should exist"))
+        .filter(|content| content.value.kind != KIND_BLOB)
+}
+
+/// Returns the blob holding a file's content, if its content was not parsed.
+pub(crate) fn file_blob(tree: &HashTree<Node>, file: &HashTreeNode<Node>) -> Option<BlobRef> {
+    file.children
+        .first()
+        .map(|id| tree.get_node(*id).expect("Content node should exist"))
+        .and_then(|content| BlobRef::from_node(&content.value))
+}
+
+/// Returns the blob holding the source of a file, if its content was parsed.
+pub(crate) fn file_source(tree: &HashTree<Node>, file: &HashTreeNode<Node>) -> Option<BlobRef> {
+    file.children
+        .iter()
+        .map(|id| tree.get_node(*id).expect("Source node should exist"))
+        .find(|source| source.value.role.as_deref() == Some(ROLE_SOURCE))
+        .and_then(|source| BlobRef::from_node(&source.value))
+}
+
+/// Returns the blobs of a file, i.e. the one of its content or the one of its source.
+pub(crate) fn file_blobs<'a>(
+    tree: &'a HashTree<Node>,
+    file: &'a HashTreeNode<Node>,
+) -> impl Iterator<Item = BlobRef> + 'a {
+    file.children
+        .iter()
+        .map(|id| tree.get_node(*id).expect("Child node should exist"))
+        .filter_map(|child| BlobRef::from_node(&child.value))
+}
+
+/// Reads the content of a blob from the store, or from the file at `working` if it is not
+/// downloaded but the file still has that content.
+fn read_blob(blobs: &BlobStore, blob: &BlobRef, working: &Path) -> Option<Vec<u8>> {
+    blobs.read(blob).or_else(|| {
+        let unchanged = BlobRef::of_file(working, None).ok() == Some(*blob);
+        unchanged.then(|| fs::read(working).ok()).flatten()
+    })
+}
+
+/// Reads the source of a parsed file, see [`read_blob`].
+fn read_source(blobs: &BlobStore, source: &BlobRef, working: &Path) -> Option<String> {
+    read_blob(blobs, source, working).and_then(|source| String::from_utf8(source).ok())
+}
+
+impl TitRepository {
+    /// The content of every file of a tree, rendered from its syntax tree or read from its
+    /// blob. Parsed files take the formatting of their committed source, or of the working
+    /// copy while the source is not downloaded.
+    pub fn file_contents<'a>(
+        &'a self,
+        tree: &'a HashTree<Node>,
+    ) -> impl Iterator<Item = (String, io::Result<Vec<u8>>)> + 'a {
+        let blobs = self.blob_store();
+        collect_files(tree).into_iter().map(move |(path, file)| {
+            let previous = fs::read_to_string(self.root().join(&path)).ok();
+            let content = self.file_bytes(tree, &path, file, previous.as_deref(), &blobs);
+            (path, content)
+        })
+    }
+
+    /// The content of a file, see [`TitRepository::file_contents`].
+    pub(crate) fn file_bytes(
+        &self,
+        tree: &HashTree<Node>,
+        path: &str,
+        file: &HashTreeNode<Node>,
+        previous: Option<&str>,
+        blobs: &BlobStore,
+    ) -> io::Result<Vec<u8>> {
+        // Contents of files that are not committed yet are only in the working copy.
+        let working = self.root().join(path);
+        match file_blob(tree, file) {
+            Some(blob) => read_blob(blobs, &blob, &working)
+                .ok_or_else(|| io::Error::other(format!("Content of {} is not downloaded", path))),
+            None => Ok(self
+                .content_format()
+                .zip(file_content(tree, file))
+                .and_then(|(format, content)| {
+                    let content = TitTree::from_hash_node(tree, content);
+                    let source = file_source(tree, file)
+                        .and_then(|source| read_source(blobs, &source, &working));
+                    format.render(Path::new(path), &content, source.as_deref().or(previous))
+                })
+                .unwrap_or_default()
+                .into_bytes()),
+        }
+    }
+}
+
+/// State of a directory scan, see [`build_hash_tree_with_cache`].
+struct Scan<'a> {
+    format: Option<&'a dyn ContentFormat>,
+    previous_files: BTreeMap<String, &'a HashTreeNode<Node>>,
+    cache: &'a TreeCache,
+    sparse: &'a SparseSelection,
+    blobs: Option<&'a BlobStore>,
+}
+
+/// State of merging the scanned entries into a tree, see [`add_scanned_entry`].
+struct Merge<'a> {
+    previous: &'a HashTree<Node>,
+    sparse: &'a SparseSelection,
+    /// Nodes inserted without their hash, parents first.
+    inserted: Vec<usize>,
+    /// Relative path, stamp and node of every scanned file.
+    files: Vec<(String, FileStamp, usize)>,
+}
+
+/// A directory or file found by the scan, with the subtrees of its files already built.
+enum ScannedEntry<'a> {
+    Dir {
+        name: String,
+        relative: String,
+        children: Vec<ScannedEntry<'a>>,
+    },
+    File {
+        name: String,
+        relative: String,
+        stamp: FileStamp,
+        content: ScannedContent<'a>,
+    },
+}
+
+impl ScannedEntry<'_> {
+    fn name(&self) -> &str {
+        match self {
+            ScannedEntry::Dir { name, .. } | ScannedEntry::File { name, .. } => name,
+        }
+    }
+}
+
+enum ScannedContent<'a> {
+    /// The file is unchanged, its node is taken from the previous tree.
+    Unchanged(&'a HashTreeNode<Node>),
+    /// The file was parsed, its source is kept as a blob next to its syntax tree.
+    Parsed(HashTree<Node>, BlobRef),
+    Blob(BlobRef),
+}
+
+/// Walks a directory on the thread pool, parsing files that changed since the last scan.
+fn scan_fs_entry<'a>(path: &Path, relative: &str, scan: &Scan<'a>) -> Option<ScannedEntry<'a>> {
     let name = path.file_name().unwrap().to_str().unwrap().to_string();
     if path.is_dir() {
-        let dir_node = Node {
-            kind: KIND_DIR.to_string(),
-            value: Some(name.clone()),
-            role: None,
-        };
-        let new_node_id = match parent {
-            Some(parent) => arena
-                .insert(parent, dir_node)
-                .expect("Failed to insert node"),
-            None => arena.insert_root(dir_node),
-        };
-
         let ignored = get_ignorelist_of_dir(path);
         let included = path
             .read_dir()
             .unwrap()
-            .filter_map(|e| match e {
-                Ok(e) => Some(e),
-                Err(_) => None,
-            })
+            .filter_map(|e| e.ok())
             .map(|e| e.path())
             .filter(|e| !ignored.contains(&e.file_name().unwrap().to_str().unwrap().to_string()))
             .collect::<Vec<_>>();
 
-        for entry in included {
-            scan_and_add_fs_entry(arena, Some(new_node_id), &entry);
-        }
-    } else {
-        let content = "Load real content as AST later...".to_string();
-        let file_node = Node {
-            kind: KIND_FILE.to_string(),
-            value: Some(name.clone()),
-            role: Some(content),
-        };
-
-        arena
-            .insert(
-                parent.expect("Cannot insert file without parent!"),
-                file_node,
-            )
-            .expect("Failed to insert node");
+        let mut children: Vec<_> = included
+            .par_iter()
+            .filter_map(|entry| {
+                let entry_name = entry.file_name().unwrap().to_str().unwrap();
+                let entry_relative = join_relative(relative, entry_name);
+                match scan.sparse.includes(&entry_relative) {
+                    true => scan_fs_entry(entry, &entry_relative, scan),
+                    false => None,
+                }
+            })
+            .collect();
+        // Entries are sorted, so the tree does not depend on the order the file system
+        // lists them in.
+        children.sort_by(|a, b| a.name().cmp(b.name()));
+
+        return Some(ScannedEntry::Dir {
+            name,
+            relative: relative.to_string(),
+            children,
+        });
+    }
+
+    // The stamp is taken before reading, so a write during the scan invalidates it.
+    let stamp = FileStamp::from(&fs::metadata(path).ok()?);
+
+    let unchanged = scan.previous_files.get(relative).filter(|previous| {
+        scan.cache.get(relative)
+            == Some(&CachedFile {
+                stamp,
+                hash: previous.hash,
+            })
+    });
+    let parsed = match unchanged {
+        Some(previous) => Some(ScannedContent::Unchanged(previous)),
+        None => scan.format.and_then(|format| {
+            let source = fs::read_to_string(path).ok()?;
+            let content = format.parse(path, &source)?;
+            let source = BlobRef::of_bytes(source.as_bytes(), scan.blobs).ok()?;
+            Some(ScannedContent::Parsed(HashTree::from(&content), source))
+        }),
+    };
+    // Files that cannot be read are left out, like files removed during the scan.
+    let content = match parsed {
+        Some(content) => content,
+        None => ScannedContent::Blob(BlobRef::of_file(path, scan.blobs).ok()?),
+    };
+
+    Some(ScannedEntry::File {
+        name,
+        relative: relative.to_string(),
+        stamp,
+        content,
+    })
+}
+
+fn join_relative(relative: &str, name: &str) -> String {
+    match relative {
+        "" => name.to_string(),
+        _ => format!("{}/{}", relative, name),
+    }
+}
+
+/// Adds a scanned entry to the tree. `previous_node` is the node of the entry in the
+/// previous tree, if it had one.
+fn add_scanned_entry(
+    arena: &mut HashTree<Node>,
+    parent: Option<usize>,
+    entry: ScannedEntry,
+    previous_node: Option<&HashTreeNode<Node>>,
+    merge: &mut Merge,
+) {
+    let previous = merge.previous;
+    let sparse = merge.sparse;
+    match entry {
+        ScannedEntry::Dir {
+            name,
+            relative,
+            children,
+        } => {
+            let dir_node = Node {
+                kind: KIND_DIR.to_string(),
+                value: Some(name),
+                role: None,
+            };
+            let new_node_id = match parent {
+                Some(parent) => arena
+                    .insert(parent, dir_node)
+                    .expect("Failed to insert node"),
+                None => arena.insert_root(dir_node),
+            };
+            merge.inserted.push(new_node_id);
+
+            let previous_dir = previous_node.filter(|_| sparse.is_partial(&relative));
+            let previous_dir = match previous_dir {
+                Some(previous_dir) => previous_dir,
+                None => {
+                    // Below a directory that is checked out as a whole, everything is scanned.
+                    for child in children {
+                        add_scanned_entry(arena, Some(new_node_id), child, None, merge);
+                    }
+                    return;
+                }
+            };
+
+            // Only some entries of the directory were scanned. The others keep their node
+            // and position from the previous tree, so they do not show up as changes.
+            let mut scanned: BTreeMap<String, ScannedEntry> = children
+                .into_iter()
+                .map(|child| (child.name().to_string(), child))
+                .collect();
+            for previous_id in &previous_dir.children {
+                let previous_child = previous.get_node(*previous_id).expect("Child should exist");
+                let child_name = previous_child.value.value.as_deref().unwrap_or_default();
+                if !sparse.includes(&join_relative(&relative, child_name)) {
+                    arena
+                        .insert_subtree(new_node_id, previous, previous_child)
+                        .expect("Failed to insert node");
+                    continue;
+                }
+                if let Some(child) = scanned.remove(child_name) {
+                    add_scanned_entry(arena, Some(new_node_id), child, Some(previous_child), merge);
+                }
+            }
+            // Entries that are new since the previous tree come last, sorted by name.
+            for child in scanned.into_values() {
+                add_scanned_entry(arena, Some(new_node_id), child, None, merge);
+            }
+        }
+        ScannedEntry::File {
+            name,
+            relative,
+            stamp,
+            content,
+        } => {
+            let parent = parent.expect("Cannot insert file without parent!");
+            let file_id = match content {
+                ScannedContent::Unchanged(node) => arena
+                    .insert_subtree(parent, previous, node)
+                    .expect("Failed to insert node"),
+                content => {
+                    let file_node = Node {
+                        kind: KIND_FILE.to_string(),
+                        value: Some(name),
+                        role: None,
+                    };
+                    let file_id = arena
+                        .insert(parent, file_node)
+                        .expect("Failed to insert node");
+                    merge.inserted.push(file_id);
+                    match content {
+                        ScannedContent::Parsed(content, source) => {
+                            let root = content.get_root().expect("Parsed content has a root");
+                            arena
+                                .insert_subtree(file_id, &content, root)
+                                .expect("Failed to insert node");
+                            let source_id = arena
+                                .insert(file_id, source.to_source_node())
+                                .expect("Failed to insert node");
+                            merge.inserted.push(source_id);
+                        }
+                        ScannedContent::Blob(blob) => {
+                            let blob_id = arena
+                                .insert(file_id, blob.to_node())
+                                .expect("Failed to insert node");
+                            merge.inserted.push(blob_id);
+                        }
+                        ScannedContent::Unchanged(_) => {}
+                    }
+                    file_id
+                }
+            };
+
+            merge.files.push((relative, stamp, file_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::build_hash_tree_for_dir;
+    use crate::testutil::{temp_dir, write_file};
+    use crate::TitTree;
+    use std::fs;
+
+    #[test]
+    fn test_scan_is_deterministic() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        for i in 0..20 {
+            for j in 0..20 {
+                write_file(dir, &format!("dir{}/file{}", i, j), "");
+            }
+        }
+
+        // Children are sorted by name, whatever order the directories are read in.
+        let tree = build_hash_tree_for_dir(dir, None);
+        let root = tree.get_root().unwrap();
+        let names: Vec<_> = root
+            .children
+            .iter()
+            .map(|id| tree.get_node(*id).unwrap().value.value.clone().unwrap())
+            .collect();
+        let mut expected: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        expected.sort();
+        assert_eq!(names, expected);
+
+        // Hashing once after the merge gives the hashes of hashing every branch.
+        let mut rehashed = build_hash_tree_for_dir(dir, None);
+        rehashed.refresh_hashes();
+        assert_ne!(root.hash, crate::EMPTY_HASH);
+        assert_eq!(rehashed.get_root().unwrap().hash, root.hash);
+
+        for _ in 0..5 {
+            let other = build_hash_tree_for_dir(dir, None);
+            assert_eq!(other.get_root().unwrap().hash, root.hash);
+            assert_eq!(TitTree::from(&other), TitTree::from(&tree));
+        }
     }
 }
@@ -0,0 +1,185 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+pub use ed25519_dalek::SigningKey;
+
+use crate::util::{bytes_to_hex, hex_to_bytes};
+use crate::{Commit, TitError};
+
+/// An ed25519 signature over the ID of a commit or the hash of a tag, together with the key
+/// that made it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommitSignature {
+    pub public_key: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+/// Result of checking the signature of a commit or tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Unsigned,
+    /// The signature matches. Holds the public key that made it, as hex.
+    Good(String),
+    /// The signature was not made for this content, or not by the key it names.
+    Bad,
+}
+
+impl Commit {
+    /// Signs the ID of the commit. As the signature is not part of what the ID is computed
+    /// from, the ID stays the same.
+    pub fn sign(&mut self, key: &SigningKey) -> Result<(), TitError<'static>> {
+        self.signature = Some(sign_hash(key, &self.id_hash()?));
+        Ok(())
+    }
+
+    /// Commits of unknown versions cannot be verified, as their ID cannot be computed.
+    pub fn verify(&self) -> Verification {
+        match self.id_hash() {
+            Ok(hash) => verify_hash(self.signature.as_ref(), &hash),
+            Err(_) => Verification::Bad,
+        }
+    }
+}
+
+pub(crate) fn sign_hash(key: &SigningKey, hash: &[u8; 32]) -> CommitSignature {
+    CommitSignature {
+        public_key: key.verifying_key().to_bytes(),
+        signature: key.sign(hash).to_vec(),
+    }
+}
+
+pub(crate) fn verify_hash(signature: Option<&CommitSignature>, hash: &[u8; 32]) -> Verification {
+    let commit_signature = match signature {
+        Some(signature) => signature,
+        None => return Verification::Unsigned,
+    };
+
+    let key = VerifyingKey::from_bytes(&commit_signature.public_key);
+    let signature = Signature::from_slice(&commit_signature.signature);
+    match (key, signature) {
+        (Ok(key), Ok(signature)) if key.verify(hash, &signature).is_ok() => {
+            Verification::Good(bytes_to_hex(&commit_signature.public_key))
+        }
+        _ => Verification::Bad,
+    }
+}
+
+impl Verification {
+    pub fn is_good(&self) -> bool {
+        matches!(self, Verification::Good(_))
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verification::Unsigned => f.write_str("unsigned"),
+            Verification::Good(key) => write!(f, "good signature by {}", &key[..16]),
+            Verification::Bad => f.write_str("BAD signature"),
+        }
+    }
+}
+
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::generate(&mut rand_core::OsRng)
+}
+
+pub fn public_key_hex(key: &SigningKey) -> String {
+    bytes_to_hex(key.verifying_key().as_bytes())
+}
+
+/// Reads a secret key stored as 64 hex digits, as written by [`write_signing_key`].
+pub fn read_signing_key(path: &Path) -> Result<SigningKey, TitError<'static>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| TitError("Failed to read signing key", Some(e)))?;
+    let bytes = hex_to_bytes(content.trim())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or(TitError("Signing key must be 64 hex digits", None))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Stores a secret key as hex, readable only by the user.
+///
+/// The key goes to a new file that is created readable only by the user, which then replaces
+/// any previous key, so the key is never readable by others, not even for a moment.
+pub fn write_signing_key(path: &Path, key: &SigningKey) -> Result<(), TitError<'static>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| TitError("Failed to create key directory", Some(e)))?;
+    }
+
+    let temp_path = path.with_extension("tmp");
+    // A leftover of an interrupted write may have other permissions, so it is not reused.
+    let _ = fs::remove_file(&temp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&temp_path)
+        .map_err(|e| TitError("Failed to write signing key", Some(e)))?;
+    file.write_all((bytes_to_hex(key.as_bytes()) + "\n").as_bytes())
+        .and_then(|_| file.sync_all())
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|e| TitError("Failed to write signing key", Some(e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_signing_key, read_signing_key, write_signing_key, Verification};
+    use crate::{Commit, PersonStamp, EMPTY_HASH};
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signature = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 0,
+            offset: 0,
+        };
+        let mut commit = Commit::new(
+            "first".to_string(),
+            vec![],
+            None,
+            EMPTY_HASH,
+            signature.clone(),
+            signature,
+        );
+        assert_eq!(commit.verify(), Verification::Unsigned);
+
+        let id = commit.get_id().unwrap();
+        commit.sign(&generate_signing_key()).unwrap();
+        assert_eq!(commit.get_id().unwrap(), id);
+        assert!(commit.verify().is_good());
+
+        commit.message = "forged".to_string();
+        assert_eq!(commit.verify(), Verification::Bad);
+    }
+
+    #[test]
+    fn test_write_signing_key() {
+        let dir = crate::testutil::temp_dir();
+        let path = dir.path().join("signing.key");
+        let key = generate_signing_key();
+        write_signing_key(&path, &key).unwrap();
+        // Replacing a key works as well.
+        write_signing_key(&path, &key).unwrap();
+
+        assert_eq!(read_signing_key(&path).unwrap().to_bytes(), key.to_bytes());
+        assert!(!path.with_extension("tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::Path;
+
+use crate::filestatus::collect_files;
+use crate::{blobs_of_tree, detect_tree_changes, write_hash_tree_to_dir, TitError, TitRepository};
+
+/// The subtrees of a sparse checkout, as paths relative to the root of the repository. Only
+/// these are scanned and written, the rest of the tree is kept as it was committed. An
+/// empty selection selects the whole tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparseSelection {
+    paths: Vec<String>,
+}
+
+impl SparseSelection {
+    /// Selects the given paths. Leading `./` and surrounding slashes are ignored, and paths
+    /// below other selected paths are dropped.
+    pub fn new<S: AsRef<str>>(paths: &[S]) -> Self {
+        let mut paths: Vec<String> = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref().trim_start_matches("./");
+                path.trim_matches('/').to_string()
+            })
+            .collect();
+        // Selecting the root selects everything.
+        if paths.iter().any(|path| path.is_empty() || path == ".") {
+            return Self::default();
+        }
+        paths.sort();
+        paths.dedup();
+        let selected = paths.clone();
+        paths.retain(|path| !selected.iter().any(|other| is_below(path, other)));
+        Self { paths }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Whether a path is one of the selected paths or lies below one.
+    pub fn contains(&self, relative: &str) -> bool {
+        self.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|path| path == relative || is_below(relative, path))
+    }
+
+    /// Whether a directory has selected paths below it but is not selected as a whole, so
+    /// only some of its entries are checked out.
+    pub fn is_partial(&self, relative: &str) -> bool {
+        !self.contains(relative)
+            && self
+                .paths
+                .iter()
+                .any(|path| relative.is_empty() || is_below(path, relative))
+    }
+
+    /// Whether an entry is checked out, either as a whole or partially.
+    pub fn includes(&self, relative: &str) -> bool {
+        self.contains(relative) || self.is_partial(relative)
+    }
+}
+
+fn is_below(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+impl TitRepository {
+    /// Limits the working directory to the given selection. Files that are no longer
+    /// selected are removed, newly selected ones are written from the signed tree. Fails if
+    /// there are uncommitted changes, as they could be lost, or if the blobs of selected
+    /// files are not downloaded.
+    pub fn set_sparse(&self, selection: SparseSelection) -> Result<(), TitError<'static>> {
+        let signed = self.signed_tree();
+        if !detect_tree_changes(&signed, &self.current_tree()).is_empty() {
+            return Err(TitError("Commit the pending changes first", None));
+        }
+        let blobs = self.blob_store();
+        let missing = blobs_of_tree(&signed, &selection)
+            .iter()
+            .any(|blob| !blobs.missing_objects(blob).is_empty());
+        if missing {
+            return Err(TitError(
+                "Contents of selected files are not downloaded",
+                None,
+            ));
+        }
+
+        let root = self.root();
+        for path in collect_files(&signed).keys() {
+            if !selection.contains(path) {
+                remove_file_and_empty_dirs(root, path)
+                    .map_err(|e| TitError("Failed to remove deselected file", Some(e)))?;
+            }
+        }
+
+        let mut state = self.state();
+        state.sparse = selection.paths().to_vec();
+        self.set_state(state);
+
+        write_hash_tree_to_dir(&signed, root, self.content_format(), &selection, &blobs);
+        self.set_signed_tree(self.current_tree());
+        Ok(())
+    }
+}
+
+/// Removes a file and then its directories up to the root, as long as they are empty.
+fn remove_file_and_empty_dirs(root: &Path, relative: &str) -> std::io::Result<()> {
+    let path = root.join(relative);
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    for dir in path.ancestors().skip(1).take_while(|dir| *dir != root) {
+        // Directories with other entries are kept.
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::SparseSelection;
+    use crate::testutil::{init_repository, temp_dir, write_file};
+    use crate::{blobs_of_tree, detect_tree_changes, file_statuses, FileStatus};
+
+    #[test]
+    fn test_sparse_selection() {
+        let selection = SparseSelection::new(&["./services/api/", "services/api/src", "docs"]);
+        assert_eq!(selection.paths(), ["docs", "services/api"]);
+
+        assert!(selection.contains("services/api"));
+        assert!(selection.contains("services/api/src/main.rs"));
+        assert!(!selection.contains("services/apis"));
+        assert!(!selection.contains("services"));
+
+        assert!(selection.is_partial(""));
+        assert!(selection.is_partial("services"));
+        assert!(!selection.is_partial("services/web"));
+        assert!(!selection.includes("services/web"));
+
+        let everything = SparseSelection::new(&["."]);
+        assert!(everything.is_empty());
+        assert!(everything.contains("any/path"));
+        assert!(!everything.is_partial(""));
+    }
+
+    #[test]
+    fn test_sparse_checkout_keeps_deselected_dirs() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path();
+        for path in [
+            "services/api/src/main.txt",
+            "services/web/index.txt",
+            "docs/readme.txt",
+            "top.txt",
+        ] {
+            write_file(dir, path, "content\n");
+        }
+        let repository = init_repository(dir);
+        let tree = repository.current_tree();
+        let blobs = blobs_of_tree(&tree, &SparseSelection::default());
+        repository.store_blobs(&tree, &blobs).unwrap();
+        repository.set_signed_tree(tree);
+
+        repository
+            .set_sparse(SparseSelection::new(&["services/api"]))
+            .unwrap();
+        assert!(dir.join("services/api/src/main.txt").exists());
+        assert!(!dir.join("services/web").exists());
+        assert!(!dir.join("docs").exists());
+        assert!(!dir.join("top.txt").exists());
+
+        let signed = repository.signed_tree();
+        assert!(detect_tree_changes(&signed, &repository.current_tree()).is_empty());
+
+        fs::write(dir.join("services/api/src/main.txt"), "changed\n").unwrap();
+        assert_eq!(
+            file_statuses(&signed, &repository.current_tree()),
+            [FileStatus::Modified(
+                "services/api/src/main.txt".to_string()
+            )]
+        );
+    }
+}
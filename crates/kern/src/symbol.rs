@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use indextree::{Arena, NodeId};
+
+use crate::rename::declared_name;
+use crate::repositorytree::file_of;
+use crate::{Change, Node, TitTree};
+
+/// Kinds of definitions whose name is held by their `declarator` role.
+const DECLARATOR_KINDS: [&str; 1] = ["function_definition"];
+/// Kinds of type definitions, named by their `name` role and defined by their `body` role.
+const TYPE_KINDS: [&str; 3] = ["struct_specifier", "union_specifier", "enum_specifier"];
+
+const ROLE_NAME: &str = "name";
+const ROLE_BODY: &str = "body";
+
+/// A commit that changed the definition of a symbol.
+#[derive(Debug, Clone)]
+pub struct SymbolChange {
+    pub commit_id: String,
+    /// Path of the file the symbol is defined in, relative to the repository root.
+    pub file: String,
+    /// Changes to the subtree of the definition, with paths relative to the definition.
+    pub changes: Vec<Change>,
+}
+
+/// Finds the definitions of functions and types with the given name, together with the
+/// path of the file each one is defined in.
+pub fn find_symbol(tree: &TitTree, name: &str) -> Vec<(String, NodeId)> {
+    let arena = tree.arena();
+    tree.root_id()
+        .descendants(arena)
+        .filter(|node| defined_name(arena, *node).as_deref() == Some(name))
+        .map(|node| (file_of(arena, node), node))
+        .collect()
+}
+
+/// Compares the definitions of a symbol before and after a commit, file by file. Definitions
+/// that appear or disappear are compared against an empty tree.
+pub(crate) fn symbol_changes(
+    commit_id: &str,
+    before: &HashMap<(String, usize), TitTree>,
+    after: &HashMap<(String, usize), TitTree>,
+) -> Vec<SymbolChange> {
+    let mut keys: Vec<_> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let empty = TitTree::default();
+    keys.into_iter()
+        .map(|key| {
+            let old = before.get(key).unwrap_or(&empty);
+            let new = after.get(key).unwrap_or(&empty);
+            SymbolChange {
+                commit_id: commit_id.to_string(),
+                file: key.0.clone(),
+                changes: old.detect_changes(new),
+            }
+        })
+        .filter(|change| !change.changes.is_empty())
+        .collect()
+}
+
+/// Copies the definitions of a symbol out of a tree, keyed by their file and their position
+/// among the definitions of that file.
+pub(crate) fn symbol_subtrees(tree: &TitTree, name: &str) -> HashMap<(String, usize), TitTree> {
+    let mut subtrees = HashMap::new();
+    for (file, node) in find_symbol(tree, name) {
+        let index = subtrees.keys().filter(|(f, _)| *f == file).count();
+        subtrees.insert((file, index), tree.subtree(node));
+    }
+    subtrees
+}
+
+fn defined_name(arena: &Arena<Node>, node: NodeId) -> Option<String> {
+    let kind = arena[node].get().kind.as_str();
+    let name = if DECLARATOR_KINDS.contains(&kind) {
+        declared_name(arena, node)?
+    } else if TYPE_KINDS.contains(&kind) {
+        let child_with_role = |role: &str| {
+            node.children(arena)
+                .find(|child| arena[*child].get().role.as_deref() == Some(role))
+        };
+        // `struct s x;` only refers to the type, it is defined where it has a body.
+        child_with_role(ROLE_BODY)?;
+        child_with_role(ROLE_NAME)?
+    } else {
+        return None;
+    };
+
+    arena[name].get().value.clone()
+}
@@ -0,0 +1,149 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+use sha3::Digest;
+
+use crate::encoding::CanonicalEncode;
+use crate::signing::{sign_hash, verify_hash};
+use crate::util::BinaryFile;
+use crate::{Commit, CommitSignature, PersonStamp, SigningKey, Verification};
+
+/// A name for a commit, e.g. of a release. Tags do not move once they are created.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    /// ID of the tagged commit.
+    pub target: String,
+    /// Set for annotated tags, lightweight tags are only a name.
+    pub annotation: Option<Annotation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub message: String,
+    pub tagger: PersonStamp,
+    /// Signature over the hash of the tag, see [`Tag::sign`].
+    pub signature: Option<CommitSignature>,
+}
+
+impl BinaryFile for Tag {}
+
+impl Tag {
+    pub fn lightweight(name: String, target: String) -> Self {
+        Self {
+            name,
+            target,
+            annotation: None,
+        }
+    }
+
+    pub fn annotated(name: String, target: String, message: String, tagger: PersonStamp) -> Self {
+        Self {
+            name,
+            target,
+            annotation: Some(Annotation {
+                message,
+                tagger,
+                signature: None,
+            }),
+        }
+    }
+
+    /// Tags are stored as files named after them, so names must be usable as file names.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\'])
+            && !name.chars().any(char::is_control)
+    }
+
+    /// Signs the hash of the canonical encoding of the tag. Lightweight tags cannot be
+    /// signed, as they have no annotation to hold the signature.
+    pub fn sign(&mut self, key: &SigningKey) {
+        let hash = self.hash();
+        if let Some(annotation) = &mut self.annotation {
+            annotation.signature = Some(sign_hash(key, &hash));
+        }
+    }
+
+    pub fn verify(&self) -> Verification {
+        let signature = self.annotation.as_ref().and_then(|a| a.signature.as_ref());
+        verify_hash(signature, &self.hash())
+    }
+
+    fn hash(&self) -> [u8; 32] {
+        let mut hasher = sha3::Sha3_256::default();
+        hasher.update(self.to_canonical_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// Tags are encoded as their name, target and, if they are annotated, message and tagger,
+/// using the primitives described at [`CanonicalEncode`]. The signature is not encoded.
+impl CanonicalEncode for Tag {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.target.encode(out);
+        match &self.annotation {
+            None => out.push(0),
+            Some(annotation) => {
+                out.push(1);
+                annotation.message.encode(out);
+                annotation.tagger.encode(out);
+            }
+        }
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.name, Commit::shorten_id(&self.target))?;
+        if let Some(annotation) = &self.annotation {
+            write!(
+                f,
+                " \"{}\" ({}, {})",
+                annotation.message,
+                annotation.tagger.name,
+                annotation.tagger.local_time()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tag;
+    use crate::{generate_signing_key, PersonStamp, Verification};
+
+    #[test]
+    fn test_sign_annotated_tag() {
+        let tagger = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 0,
+            offset: 0,
+        };
+        let key = generate_signing_key();
+
+        let mut lightweight = Tag::lightweight("v1".to_string(), "abc".to_string());
+        lightweight.sign(&key);
+        assert_eq!(lightweight.verify(), Verification::Unsigned);
+
+        let mut tag = Tag::annotated(
+            "v1".to_string(),
+            "abc".to_string(),
+            "Release".to_string(),
+            tagger,
+        );
+        tag.sign(&key);
+        assert!(tag.verify().is_good());
+
+        tag.target = "def".to_string();
+        assert_eq!(tag.verify(), Verification::Bad);
+
+        assert!(Tag::is_valid_name("v1.0.0"));
+        assert!(!Tag::is_valid_name("../v1"));
+        assert!(!Tag::is_valid_name(".hidden"));
+    }
+}
@@ -49,3 +49,8 @@ impl CheckList {
         eprintln!("\r  {} {}  ", self.failed, self.current_item);
     }
 }
+
+#[cfg(not(windows))]
+fn platform_supports_emoji() -> bool {
+    true
+}
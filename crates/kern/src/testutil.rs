@@ -0,0 +1,30 @@
+use std::fs;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use crate::TitRepository;
+
+/// Creates an empty directory for a test. It is removed when dropped, so also when an
+/// assertion of the test fails.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("tit-test-")
+        .tempdir()
+        .expect("Failed to create temporary directory")
+}
+
+/// Writes a file below `dir`, creating the directories it lies in.
+pub fn write_file(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Initializes a repository in `dir` with a `main` branch, creating the directory if needed.
+pub fn init_repository(dir: &Path) -> TitRepository {
+    fs::create_dir_all(dir).unwrap();
+    let repository = TitRepository::new(dir.to_path_buf());
+    repository.init("test", "none", "main").unwrap();
+    repository
+}
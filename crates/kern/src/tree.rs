@@ -1,19 +1,30 @@
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 use std::{fmt, fs};
 
 use indextree::{Arena, NodeId};
 use serde::{Deserialize, Serialize};
 
+use crate::encoding::{merkle_hash, NodeHash};
+use crate::hashtree::{HashTree, HashTreeNode};
 use crate::tree::node_change::NodeChange;
-use crate::util::{from_serialized_bytes, to_serialized_bytes, BinaryFileRead, BinaryFileWrite};
+use crate::util::{from_serialized_bytes, from_serialized_bytes_exact, to_serialized_bytes};
+use crate::util::{BinaryFileRead, BinaryFileWrite};
 use crate::{Change, Node, Path, TitError};
 
 mod node_change;
+mod source_map;
 
-#[derive(Serialize, Deserialize)]
+pub use source_map::*;
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TitTree {
     arena: Arena<Node>,
     root: NodeId,
+    /// Merkle hash of each node, computed like the hashes of a [`HashTree`]. Nodes without
+    /// a hash are compared by their contents.
+    #[serde(skip)]
+    hashes: HashMap<NodeId, NodeHash>,
 }
 
 impl Default for TitTree {
@@ -24,7 +35,7 @@ impl Default for TitTree {
             value: None,
             role: None,
         });
-        Self { arena, root }
+        Self::new(arena, root)
     }
 }
 
@@ -35,7 +46,16 @@ impl<P: AsRef<std::path::Path>> BinaryFileRead<P> for TitTree {
             .expect("Failed to open tree file!")
             .read_to_end(&mut compressed_bytes)
             .expect("Failed to read tree file!");
-        from_serialized_bytes(&compressed_bytes).expect("Failed to deserialize tree")
+        let mut tree: TitTree =
+            from_serialized_bytes(&compressed_bytes).expect("Failed to deserialize tree");
+        tree.refresh_hashes();
+        tree
+    }
+
+    fn try_read_from(path: P) -> Option<Self> {
+        let mut tree: TitTree = from_serialized_bytes_exact(&fs::read(path).ok()?).ok()?;
+        tree.refresh_hashes();
+        Some(tree)
     }
 }
 
@@ -44,29 +64,99 @@ impl<P: AsRef<std::path::Path>> BinaryFileWrite<P> for TitTree {
         let compressed_bytes = to_serialized_bytes(self).expect("Failed to serialize tree");
         fs::File::create(path)
             .expect("Failed to create file!")
-            .write(&compressed_bytes)
+            .write_all(&compressed_bytes)
             .expect("Failed to write tree!");
     }
 }
 
 impl TitTree {
     pub fn new(arena: Arena<Node>, root: NodeId) -> Self {
-        TitTree { arena, root }
+        let mut tree = TitTree {
+            arena,
+            root,
+            hashes: HashMap::new(),
+        };
+        tree.refresh_hashes();
+        tree
     }
 
+    /// Computes the changes that turn this tree into another. Subtrees with equal hashes
+    /// are skipped without visiting them.
     pub fn detect_changes(&self, other: &TitTree) -> Vec<Change> {
         detect_changes_in_nodes(
             Some(&self.root),
             Some(&other.root),
-            &self.arena,
-            &other.arena,
+            self,
+            other,
             &mut vec![0],
         )
     }
 
-    pub fn apply_changes(&mut self, changes: &Vec<Change>) {
+    /// The Merkle hash of a node, if it is up to date.
+    pub fn hash(&self, node: NodeId) -> Option<NodeHash> {
+        self.hashes.get(&node).copied()
+    }
+
+    /// Computes the hashes of all nodes, e.g. after the arena was changed directly.
+    pub fn refresh_hashes(&mut self) {
+        self.hashes.clear();
+        let nodes: Vec<_> = self.root.descendants(&self.arena).collect();
+        for node in nodes.into_iter().rev() {
+            self.refresh_hash_at(node);
+        }
+    }
+
+    /// Recomputes the hashes of the given nodes and all of their ancestors, deepest first.
+    fn refresh_hashes_of_branches(&mut self, nodes: impl IntoIterator<Item = NodeId>) {
+        let mut dirty = HashSet::new();
+        for node in nodes {
+            if self.arena.get(node).is_none_or(|n| n.is_removed()) {
+                continue;
+            }
+            for ancestor in node.ancestors(&self.arena) {
+                if !dirty.insert(ancestor) {
+                    break;
+                }
+            }
+        }
+
+        let mut dirty: Vec<_> = dirty
+            .into_iter()
+            .map(|node| (node.ancestors(&self.arena).count(), node))
+            .collect();
+        dirty.sort_by_key(|(depth, _)| std::cmp::Reverse(*depth));
+        for (_, node) in dirty {
+            self.refresh_hash_at(node);
+        }
+    }
+
+    fn refresh_hash_at(&mut self, node: NodeId) {
+        let child_hashes: Option<Vec<NodeHash>> = node
+            .children(&self.arena)
+            .map(|child| self.hashes.get(&child).copied())
+            .collect();
+        match child_hashes {
+            Some(child_hashes) => {
+                let hash = merkle_hash(self.arena[node].get(), child_hashes.into_iter());
+                self.hashes.insert(node, hash);
+            }
+            None => {
+                self.hashes.remove(&node);
+            }
+        }
+    }
+
+    pub fn apply_changes(&mut self, changes: &[Change]) {
+        self.apply_changes_tracked(changes);
+    }
+
+    /// Applies the changes and returns the nodes they added or updated. Nodes keep their
+    /// id while the changes shift them to other paths.
+    pub fn apply_changes_tracked(&mut self, changes: &[Change]) -> Vec<NodeId> {
         let change_refs: Vec<&Change> = changes.iter().collect();
         let node_changes = construct_changed_nodes(self.root, &change_refs, &mut self.arena, 0, 0);
+        let mut touched = Vec::new();
+        let mut changed = Vec::new();
 
         for node_change in node_changes {
             let node = self
@@ -80,15 +170,27 @@ impl TitTree {
                     node.kind = new_node.kind.to_string();
                     node.value = new_node.value.clone();
                     node.role = new_node.role.clone();
+                    touched.push(node_change.node_id());
+                    changed.push(node_change.node_id());
                 }
                 NodeChange::Addition(_, parent) => {
                     parent.append(node_change.node_id(), &mut self.arena);
+                    touched.push(node_change.node_id());
+                    changed.push(node_change.node_id());
                 }
                 NodeChange::Deletion(_) => {
-                    node_change.node_id().remove_subtree(&mut self.arena);
+                    let node_id = node_change.node_id();
+                    touched.extend(self.arena[node_id].parent());
+                    for removed in node_id.descendants(&self.arena) {
+                        self.hashes.remove(&removed);
+                    }
+                    node_id.remove_subtree(&mut self.arena);
                 }
             }
         }
+
+        self.refresh_hashes_of_branches(touched);
+        changed
     }
 
     pub fn root(&self) -> Result<&indextree::Node<Node>, TitError<'static>> {
@@ -101,10 +203,7 @@ impl TitTree {
         &self,
         node: &indextree::Node<Node>,
     ) -> Result<impl Iterator<Item = &indextree::Node<Node>>, TitError<'static>> {
-        let node_id = self
-            .arena
-            .get_node_id(node)
-            .ok_or(TitError("Node not found", None))?;
+        let node_id = self.node_id(node).ok_or(TitError("Node not found", None))?;
 
         let children = node_id
             .children(&self.arena)
@@ -112,6 +211,101 @@ impl TitTree {
 
         Ok(children)
     }
+
+    pub fn node_id(&self, node: &indextree::Node<Node>) -> Option<NodeId> {
+        self.arena.get_node_id(node)
+    }
+
+    /// Looks up the node at a path, where the root is at `[0]`.
+    pub fn node_id_at(&self, path: &[usize]) -> Option<NodeId> {
+        let (first, rest) = path.split_first()?;
+        if *first != 0 {
+            return None;
+        }
+
+        rest.iter().try_fold(self.root, |node, index| {
+            node.children(&self.arena).nth(*index)
+        })
+    }
+
+    pub fn root_id(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn arena(&self) -> &Arena<Node> {
+        &self.arena
+    }
+
+    /// Gives direct access to the nodes. The hashes are dropped, as they cannot be kept up
+    /// to date, until [`TitTree::refresh_hashes`] is called.
+    pub fn arena_mut(&mut self) -> &mut Arena<Node> {
+        self.hashes.clear();
+        &mut self.arena
+    }
+
+    /// Copies the subtree below a node into a tree of its own.
+    pub fn subtree(&self, node: NodeId) -> TitTree {
+        let mut arena = Arena::new();
+        let root = arena.new_node(self.arena[node].get().clone());
+        copy_children(&self.arena, node, &mut arena, root);
+
+        TitTree::new(arena, root)
+    }
+
+    /// Builds a tree from the subtree of a hash tree node.
+    /// The hashes of the hash tree are kept, if it computes them.
+    pub(crate) fn from_hash_node(tree: &HashTree<Node>, hash_node: &HashTreeNode<Node>) -> Self {
+        let mut arena = Arena::new();
+        let root = arena.new_node(hash_node.value.clone());
+        let mut hashes = HashMap::new();
+        hashes.insert(root, hash_node.hash);
+        append_hash_tree_children(tree, hash_node, root, &mut arena, &mut hashes);
+
+        if !tree.should_compute_hashes() {
+            return TitTree::new(arena, root);
+        }
+        TitTree {
+            arena,
+            root,
+            hashes,
+        }
+    }
+
+    /// Inserts this tree, root included, as the last child of a hash tree node.
+    pub(crate) fn insert_into(&self, hash_tree: &mut HashTree<Node>, parent: usize) {
+        let subtree = HashTree::from(self);
+        let root = subtree.get_root().expect("Root should exist");
+        hash_tree
+            .insert_subtree(parent, &subtree, root)
+            .expect("Failed to insert subtree");
+    }
+}
+
+impl From<&HashTree<Node>> for TitTree {
+    fn from(tree: &HashTree<Node>) -> Self {
+        match tree.get_root() {
+            Some(root) => TitTree::from_hash_node(tree, root),
+            None => TitTree::default(),
+        }
+    }
+}
+
+impl From<&TitTree> for HashTree<Node> {
+    fn from(tree: &TitTree) -> Self {
+        let mut hash_tree = HashTree::default();
+        let root = tree.arena.get(tree.root).expect("Root should exist").get();
+
+        if root.kind.is_empty() && tree.root.children(&tree.arena).next().is_none() {
+            return hash_tree;
+        }
+
+        hash_tree.set_should_compute_hashes(false);
+        let hash_root = hash_tree.insert_root(root.clone());
+        insert_tit_tree_children(tree, tree.root, hash_root, &mut hash_tree);
+        hash_tree.set_should_compute_hashes(true);
+
+        hash_tree
+    }
 }
 
 impl fmt::Debug for TitTree {
@@ -130,14 +324,21 @@ impl PartialEq for TitTree {
 fn detect_changes_in_nodes(
     n1: Option<&NodeId>,
     n2: Option<&NodeId>,
-    arena1: &Arena<Node>,
-    arena2: &Arena<Node>,
+    tree1: &TitTree,
+    tree2: &TitTree,
     path: &mut Path,
 ) -> Vec<Change> {
     let mut differences = Vec::new();
+    let (arena1, arena2) = (&tree1.arena, &tree2.arena);
 
     match (n1, n2) {
         (Some(n1), Some(n2)) => {
+            if let (Some(hash1), Some(hash2)) = (tree1.hash(*n1), tree2.hash(*n2)) {
+                if hash1 == hash2 {
+                    return differences;
+                }
+            }
+
             let node1 = arena1
                 .get(*n1)
                 .expect("Node 1 should exist in arena 1")
@@ -147,12 +348,8 @@ fn detect_changes_in_nodes(
                 .expect("Node 2 should exist in arena 2")
                 .get();
 
-            if node1.kind != node2.kind
-                || node1.role != node2.role
-                || (n1.children(arena1).peekable().peek().is_none()
-                    && n2.children(arena2).peekable().peek().is_none()
-                    && node1.value != node2.value)
-            {
+            // Inner nodes have values too, e.g. directories their name.
+            if node1 != node2 {
                 differences.push(Change::Update(path.to_vec(), node2.clone()))
             }
 
@@ -161,7 +358,7 @@ fn detect_changes_in_nodes(
             {
                 path.push(index);
                 let child_diffs =
-                    detect_changes_in_nodes(child1.as_ref(), child2.as_ref(), arena1, arena2, path);
+                    detect_changes_in_nodes(child1.as_ref(), child2.as_ref(), tree1, tree2, path);
                 differences.extend(child_diffs);
                 path.pop();
             }
@@ -179,7 +376,7 @@ fn detect_changes_in_nodes(
 
             for (index, child) in n2.children(arena2).enumerate() {
                 path.push(index);
-                let child_diffs = detect_changes_in_nodes(None, Some(&child), arena1, arena2, path);
+                let child_diffs = detect_changes_in_nodes(None, Some(&child), tree1, tree2, path);
                 differences.extend(child_diffs);
                 path.pop();
             }
@@ -281,6 +478,43 @@ fn zip_children_with_none(
         .take(max_len)
 }
 
+fn copy_children(from: &Arena<Node>, node: NodeId, to: &mut Arena<Node>, copy: NodeId) {
+    for child in node.children(from) {
+        let child_copy = copy.append_value(from[child].get().clone(), to);
+        copy_children(from, child, to, child_copy);
+    }
+}
+
+fn append_hash_tree_children(
+    tree: &HashTree<Node>,
+    hash_node: &HashTreeNode<Node>,
+    node: NodeId,
+    arena: &mut Arena<Node>,
+    hashes: &mut HashMap<NodeId, NodeHash>,
+) {
+    for child_id in &hash_node.children {
+        let hash_child = tree.get_node(*child_id).expect("Child should exist");
+        let child = node.append_value(hash_child.value.clone(), arena);
+        hashes.insert(child, hash_child.hash);
+        append_hash_tree_children(tree, hash_child, child, arena, hashes);
+    }
+}
+
+fn insert_tit_tree_children(
+    tree: &TitTree,
+    node: NodeId,
+    hash_node: usize,
+    hash_tree: &mut HashTree<Node>,
+) {
+    for child in node.children(&tree.arena) {
+        let value = tree.arena.get(child).expect("Child should exist").get();
+        let hash_child = hash_tree
+            .insert(hash_node, value.clone())
+            .expect("Failed to insert node");
+        insert_tit_tree_children(tree, child, hash_child, hash_tree);
+    }
+}
+
 fn nodes_equal(n1: &NodeId, n2: &NodeId, arena1: &Arena<Node>, arena2: &Arena<Node>) -> bool {
     let node1 = arena1
         .get(*n1)
@@ -302,7 +536,7 @@ fn nodes_equal(n1: &NodeId, n2: &NodeId, arena1: &Arena<Node>, arena2: &Arena<No
         return false;
     }
 
-    for (child1, child2) in children1.into_iter().zip(children2.into_iter()) {
+    for (child1, child2) in children1.into_iter().zip(children2) {
         if !nodes_equal(&child1, &child2, arena1, arena2) {
             return false;
         }
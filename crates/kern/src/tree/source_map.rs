@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use indextree::NodeId;
+
+use crate::Node;
+
+/// Where a node came from in the source it was parsed from.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// The node as it was parsed, used to tell whether it was changed since.
+    pub node: Node,
+    /// Number of children the node was parsed with.
+    pub children: usize,
+}
+
+/// Side table keeping the original source of a parsed tree, so whitespace, comments and
+/// punctuation between nodes can be reproduced for the parts of the tree that did not change.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    source: String,
+    spans: HashMap<NodeId, Span>,
+}
+
+impl SourceMap {
+    pub fn new(source: String) -> Self {
+        Self {
+            source,
+            spans: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, node_id: NodeId, span: Span) {
+        self.spans.insert(node_id, span);
+    }
+
+    pub fn span(&self, node_id: NodeId) -> Option<&Span> {
+        self.spans.get(&node_id)
+    }
+
+    pub fn span_mut(&mut self, node_id: NodeId) -> Option<&mut Span> {
+        self.spans.get_mut(&node_id)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn text(&self, start: usize, end: usize) -> &str {
+        &self.source[start..end]
+    }
+}
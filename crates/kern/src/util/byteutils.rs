@@ -6,7 +6,7 @@ pub fn struct_to_byte_slice<T>(data: &T) -> &[u8] {
 }
 
 pub fn slice_to_byte_slice<T>(data: &[T]) -> &[u8] {
-    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * size_of::<T>()) }
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
 }
 
 pub fn bytes_to_hex(bytes: &[u8]) -> String {
@@ -16,6 +16,18 @@ pub fn bytes_to_hex(bytes: &[u8]) -> String {
     })
 }
 
+/// Reads bytes written by [`bytes_to_hex`], or `None` if the string is not valid hex.
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
 pub fn to_serialized_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, EncodeError> {
     let config = bincode::config::standard();
     let bytes = bincode::serde::encode_to_vec::<_, _>(value, config)?;
@@ -28,8 +40,23 @@ pub fn from_serialized_bytes<T: DeserializeOwned>(
     serialized_bytes: &[u8],
 ) -> Result<T, DecodeError> {
     let config = bincode::config::standard();
-    let bytes = miniz_oxide::inflate::decompress_to_vec(&serialized_bytes)
-        .expect("Failed to decompress commit!");
+    let bytes = miniz_oxide::inflate::decompress_to_vec(serialized_bytes)
+        .map_err(|_| DecodeError::Other("Failed to decompress"))?;
     let (value, _) = bincode::serde::decode_from_slice(&bytes, config)?;
     Ok(value)
 }
+
+/// Like [`from_serialized_bytes`], but fails if bytes are left over, e.g. because they were
+/// written with another layout.
+pub fn from_serialized_bytes_exact<T: DeserializeOwned>(
+    serialized_bytes: &[u8],
+) -> Result<T, DecodeError> {
+    let config = bincode::config::standard();
+    let bytes = miniz_oxide::inflate::decompress_to_vec(serialized_bytes)
+        .map_err(|_| DecodeError::Other("Failed to decompress"))?;
+    let (value, read) = bincode::serde::decode_from_slice(&bytes, config)?;
+    match read == bytes.len() {
+        true => Ok(value),
+        false => Err(DecodeError::Other("Unexpected trailing bytes")),
+    }
+}
@@ -1,4 +1,4 @@
-use super::{from_serialized_bytes, to_serialized_bytes};
+use super::{from_serialized_bytes, from_serialized_bytes_exact, to_serialized_bytes};
 use crate::DOT_TIT;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
@@ -18,8 +18,12 @@ pub fn find_tit_root() -> Option<PathBuf> {
 
 pub trait BinaryFile {}
 
-pub trait BinaryFileRead<P> {
+pub trait BinaryFileRead<P>: Sized {
     fn read_from(path: P) -> Self;
+
+    /// Reads the file like `read_from`, or returns `None` if it cannot be read or was
+    /// written with another layout.
+    fn try_read_from(path: P) -> Option<Self>;
 }
 
 pub trait BinaryFileWrite<P> {
@@ -39,6 +43,10 @@ where
             .expect("Failed to read binary file!");
         from_serialized_bytes(&compressed_bytes).expect("Failed to deserialize binary file")
     }
+
+    fn try_read_from(path: P) -> Option<Self> {
+        from_serialized_bytes_exact(&fs::read(path).ok()?).ok()
+    }
 }
 
 impl<P, T> BinaryFileWrite<P> for T
@@ -50,7 +58,7 @@ where
         let compressed_bytes = to_serialized_bytes(self).expect("Failed to serialize!");
         fs::File::create(path)
             .expect("Failed to create file!")
-            .write(&compressed_bytes)
+            .write_all(&compressed_bytes)
             .expect("Failed to write commit!");
     }
 }
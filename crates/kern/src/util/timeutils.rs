@@ -5,4 +5,81 @@ pub fn get_epoch_millis() -> u128 {
         .duration_since(UNIX_EPOCH)
         .expect("Failed to get time!")
         .as_millis()
-}
\ No newline at end of file
+}
+
+/// Offset of the local timezone from UTC at the given time, in minutes east of UTC.
+#[cfg(unix)]
+pub fn local_offset_minutes(epoch_millis: u128) -> i32 {
+    let time = (epoch_millis / 1000) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    match unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        true => 0,
+        false => (tm.tm_gmtoff / 60) as i32,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn local_offset_minutes(_epoch_millis: u128) -> i32 {
+    0
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM:SS +HHMM` in the timezone with the given offset.
+pub fn format_timestamp(epoch_millis: u128, offset_minutes: i32) -> String {
+    let seconds = (epoch_millis / 1000) as i64 + offset_minutes as i64 * 60;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset = offset_minutes.unsigned_abs();
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}{:02}",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        sign,
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// Converts days since the epoch into a date of the proleptic Gregorian calendar, see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::format_timestamp;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0, 0), "1970-01-01 00:00:00 +0000");
+        assert_eq!(
+            format_timestamp(1_709_210_096_000, 120),
+            "2024-02-29 14:34:56 +0200"
+        );
+        assert_eq!(
+            format_timestamp(1_709_210_096_000, -330),
+            "2024-02-29 07:04:56 -0530"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{read_message, write_message, NetworkError, TitClientMessage, TitServerMessage};
-use kern::Commit;
+use kern::{BlobRef, BlobStore, Commit, Tag};
 use std::{collections::BTreeMap, net::TcpStream};
 
 #[derive(Debug)]
@@ -47,20 +47,13 @@ impl TitClient {
         write_message(&mut self.stream, TitClientMessage::DownloadFile { id })?;
         let message = read_message::<TitServerMessage>(&mut self.stream)?;
         match message {
-            TitServerMessage::CommitFile { commit } => Ok(commit.clone()),
+            TitServerMessage::CommitFile { commit } => Ok(*commit),
             _ => Err(NetworkError::UnexpectedMessage),
         }
     }
 
-    pub fn offer_content(
-        &mut self,
-        commits: Vec<String>,
-        branches: BTreeMap<String, String>,
-    ) -> Result<Vec<String>, NetworkError> {
-        write_message(
-            &mut self.stream,
-            TitClientMessage::OfferContent { commits, branches },
-        )?;
+    pub fn offer_content(&mut self, commits: Vec<String>) -> Result<Vec<String>, NetworkError> {
+        write_message(&mut self.stream, TitClientMessage::OfferContent { commits })?;
 
         let message = read_message::<TitServerMessage>(&mut self.stream)?;
         match message {
@@ -74,13 +67,134 @@ impl TitClient {
         for change in changes {
             write_message(
                 &mut self.stream,
-                TitClientMessage::UploadChanges { changes: change },
+                TitClientMessage::UploadChanges {
+                    changes: Box::new(change),
+                },
             )?;
         }
+        let mut rejected = false;
         for _ in 0..change_count {
-            read_message::<TitServerMessage>(&mut self.stream)?;
+            let message = read_message::<TitServerMessage>(&mut self.stream)?;
+            rejected |= matches!(message, TitServerMessage::Error);
+        }
+
+        match rejected {
+            true => Err(NetworkError::Rejected),
+            false => Ok(()),
+        }
+    }
+
+    pub fn download_tags(&mut self) -> Result<Vec<Tag>, NetworkError> {
+        write_message(&mut self.stream, TitClientMessage::DownloadTags)?;
+
+        let message = read_message::<TitServerMessage>(&mut self.stream)?;
+        match message {
+            TitServerMessage::Tags { tags } => Ok(tags),
+            _ => Err(NetworkError::UnexpectedMessage),
+        }
+    }
+
+    /// Uploads tags one by one and returns the names of the tags the server rejected.
+    pub fn upload_tags(&mut self, tags: Vec<Tag>) -> Result<Vec<String>, NetworkError> {
+        let mut rejected = vec![];
+        for tag in tags {
+            let name = tag.name.clone();
+            write_message(
+                &mut self.stream,
+                TitClientMessage::UploadTag { tag: Box::new(tag) },
+            )?;
+
+            match read_message::<TitServerMessage>(&mut self.stream)? {
+                TitServerMessage::Ok => {}
+                TitServerMessage::Error => rejected.push(name),
+                _ => return Err(NetworkError::UnexpectedMessage),
+            }
+        }
+
+        Ok(rejected)
+    }
+
+    /// Uploads the objects of blobs the server does not have yet.
+    pub fn upload_blobs(
+        &mut self,
+        store: &BlobStore,
+        blobs: &[BlobRef],
+    ) -> Result<(), NetworkError> {
+        let hashes = blobs.iter().flat_map(|blob| store.objects(blob)).collect();
+        write_message(&mut self.stream, TitClientMessage::OfferBlobs { hashes })?;
+        let requested = match read_message::<TitServerMessage>(&mut self.stream)? {
+            TitServerMessage::RequestBlobs { hashes } => hashes,
+            _ => return Err(NetworkError::UnexpectedMessage),
+        };
+
+        for hash in requested {
+            let data = store.read_object(&hash).ok_or(NetworkError::ReadError)?;
+            write_message(&mut self.stream, TitClientMessage::UploadBlob { data })?;
+            match read_message::<TitServerMessage>(&mut self.stream)? {
+                TitServerMessage::Ok => {}
+                TitServerMessage::Error => return Err(NetworkError::Rejected),
+                _ => return Err(NetworkError::UnexpectedMessage),
+            }
         }
 
         Ok(())
     }
+
+    /// Downloads the objects of blobs that are missing in the store. The manifests are
+    /// downloaded first, as they name the chunks.
+    pub fn download_blobs(
+        &mut self,
+        store: &BlobStore,
+        blobs: &[BlobRef],
+    ) -> Result<(), NetworkError> {
+        let mut previous = vec![];
+        loop {
+            let mut missing: Vec<_> = blobs
+                .iter()
+                .flat_map(|blob| store.missing_objects(blob))
+                .collect();
+            missing.sort();
+            missing.dedup();
+            if missing.is_empty() {
+                return Ok(());
+            }
+            // Objects that are still missing after being downloaded are not valid manifests.
+            if missing == previous {
+                return Err(NetworkError::DecodeError);
+            }
+
+            for hash in &missing {
+                let hash = *hash;
+                write_message(&mut self.stream, TitClientMessage::DownloadBlob { hash })?;
+                let data = match read_message::<TitServerMessage>(&mut self.stream)? {
+                    TitServerMessage::Blob { data } => data,
+                    TitServerMessage::Error => return Err(NetworkError::Rejected),
+                    _ => return Err(NetworkError::UnexpectedMessage),
+                };
+                // Objects are named by their hash, so a corrupted one cannot take the place
+                // of the requested one.
+                match store.write_object(&data) {
+                    Ok(stored) if stored == hash => {}
+                    Ok(_) => return Err(NetworkError::DecodeError),
+                    Err(_) => return Err(NetworkError::WriteError),
+                }
+            }
+            previous = missing;
+        }
+    }
+
+    pub fn update_branch(&mut self, name: String, commit_id: String) -> Result<(), NetworkError> {
+        write_message(
+            &mut self.stream,
+            TitClientMessage::UpdateBranch { name, commit_id },
+        )?;
+
+        let message = read_message::<TitServerMessage>(&mut self.stream)?;
+        match message {
+            TitServerMessage::Ok => Ok(()),
+            TitServerMessage::Error => Err(NetworkError::Rejected),
+            TitServerMessage::UnsignedCommits { commits } => Err(NetworkError::Unsigned(commits)),
+            _ => Err(NetworkError::UnexpectedMessage),
+        }
+    }
 }
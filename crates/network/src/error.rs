@@ -8,6 +8,10 @@ pub enum NetworkError {
     ReadError,
     WriteError,
     UnexpectedMessage,
+    Rejected,
+    /// The server only accepts signed commits on the branch, see
+    /// [`crate::TitServerMessage::UnsignedCommits`].
+    Unsigned(Vec<String>),
 }
 
 impl Display for NetworkError {
@@ -32,11 +32,35 @@ pub enum TitClientMessage {
         id: String,
     },
     UploadChanges {
-        changes: kern::Commit,
+        changes: Box<kern::Commit>,
     },
+    /// Commits the client can upload. Answered with `RequestUpload` listing the ones the
+    /// server does not have. Branches are only moved by `UpdateBranch`.
     OfferContent {
         commits: Vec<String>,
-        branches: BTreeMap<String, String>,
+    },
+    UpdateBranch {
+        name: String,
+        commit_id: String,
+    },
+    DownloadTags,
+    /// Answered with `Ok`, or `Error` if the server has a different tag of that name or
+    /// cannot accept the tag.
+    UploadTag {
+        tag: Box<kern::Tag>,
+    },
+    /// Blob objects, i.e. manifests and chunks, the client can upload. Answered with
+    /// `RequestBlobs` listing the ones the server does not have.
+    OfferBlobs {
+        hashes: Vec<kern::NodeHash>,
+    },
+    /// Answered with `Ok`, or `Error` if the object cannot be stored.
+    UploadBlob {
+        data: Vec<u8>,
+    },
+    /// Answered with `Blob`, or `Error` if the server does not have the object.
+    DownloadBlob {
+        hash: kern::NodeHash,
     },
 }
 
@@ -51,12 +75,26 @@ pub enum TitServerMessage {
         branches: BTreeMap<String, String>,
     },
     CommitFile {
-        commit: kern::Commit,
+        commit: Box<kern::Commit>,
     },
     RepositoryCreated,
     RequestUpload {
         commits: Vec<String>,
     },
+    /// The branch only accepts commits signed by trusted keys, but these are unsigned, badly
+    /// signed or signed by other keys.
+    UnsignedCommits {
+        commits: Vec<String>,
+    },
+    Tags {
+        tags: Vec<kern::Tag>,
+    },
+    RequestBlobs {
+        hashes: Vec<kern::NodeHash>,
+    },
+    Blob {
+        data: Vec<u8>,
+    },
 }
 
 pub fn write_message<T: Serialize>(stream: &mut TcpStream, message: T) -> Result<(), NetworkError> {
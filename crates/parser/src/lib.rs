@@ -1,3 +1,5 @@
 pub mod macros;
 pub mod parser;
 pub mod kinds;
+pub mod normalize;
+pub mod registry;
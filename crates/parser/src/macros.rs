@@ -3,4 +3,25 @@ macro_rules! c {
     () => {
         tree_sitter_c::LANGUAGE.into()
     };
-}
\ No newline at end of file
+}
+
+#[macro_export]
+macro_rules! rust {
+    () => {
+        tree_sitter_rust::LANGUAGE.into()
+    };
+}
+
+#[macro_export]
+macro_rules! python {
+    () => {
+        tree_sitter_python::LANGUAGE.into()
+    };
+}
+
+#[macro_export]
+macro_rules! javascript {
+    () => {
+        tree_sitter_javascript::LANGUAGE.into()
+    };
+}
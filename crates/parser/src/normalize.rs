@@ -0,0 +1,163 @@
+use indextree::{Arena, NodeId};
+use kern::{Node, TitTree};
+use serde::Deserialize;
+
+use crate::kinds::Kinds;
+
+const ROLE_LEFT: &str = "left";
+const ROLE_RIGHT: &str = "right";
+const ROLE_OPERATOR: &str = "operator";
+const INTEGER_SUFFIXES: [char; 4] = ['u', 'U', 'l', 'L'];
+
+/// A rewrite that puts syntax trees into a canonical form, so that cosmetic changes
+/// compare equal. Rules are listed per language in `language.toml`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Normalization {
+    /// Orders the operands of commutative binary operators, e.g. `b + a` becomes `a + b`.
+    /// Operands containing one of the `side_effects` kinds, e.g. calls, keep their order,
+    /// as swapping them changes the order their effects happen in.
+    CommutativeOperators {
+        kinds: Kinds,
+        operators: Kinds,
+        #[serde(default)]
+        side_effects: Kinds,
+    },
+    /// Replaces nodes by their children, e.g. redundant parentheses and braces.
+    Unwrap { kinds: Kinds },
+    /// Writes integer literals in decimal, e.g. `0x10` becomes `16`.
+    IntegerLiterals { kinds: Kinds },
+}
+
+/// Applies the rules in order to the whole tree.
+pub fn normalize(tree: &mut TitTree, rules: &[Normalization]) {
+    let root = tree.root_id();
+    for rule in rules {
+        apply_rule(tree.arena_mut(), root, rule);
+    }
+    tree.refresh_hashes();
+}
+
+fn apply_rule(arena: &mut Arena<Node>, node: NodeId, rule: &Normalization) {
+    // Children first, so operands are already canonical when they are compared.
+    let children: Vec<_> = node.children(arena).collect();
+    for child in children {
+        apply_rule(arena, child, rule);
+    }
+
+    match rule {
+        Normalization::CommutativeOperators {
+            kinds,
+            operators,
+            side_effects,
+        } => {
+            if kinds.contains(&arena[node].get().kind) {
+                order_operands(arena, node, operators, side_effects);
+            }
+        }
+        Normalization::Unwrap { kinds } => {
+            if kinds.contains(&arena[node].get().kind) && node.ancestors(arena).nth(1).is_some() {
+                unwrap(arena, node);
+            }
+        }
+        Normalization::IntegerLiterals { kinds } => {
+            let value = arena[node].get_mut();
+            if kinds.contains(&value.kind) {
+                if let Some(decimal) = value.value.as_deref().and_then(decimal_integer) {
+                    value.value = Some(decimal);
+                }
+            }
+        }
+    }
+}
+
+fn order_operands(arena: &mut Arena<Node>, node: NodeId, operators: &Kinds, side_effects: &Kinds) {
+    let children: Vec<_> = node.children(arena).collect();
+    let find = |role: &str| {
+        children
+            .iter()
+            .copied()
+            .find(|child| arena[*child].get().role.as_deref() == Some(role))
+    };
+
+    let (left, operator, right) = match (find(ROLE_LEFT), find(ROLE_OPERATOR), find(ROLE_RIGHT)) {
+        (Some(left), Some(operator), Some(right)) => (left, operator, right),
+        _ => return,
+    };
+    if !operators.contains(&arena[operator].get().kind) {
+        return;
+    }
+    let has_side_effects = |operand: NodeId| {
+        operand
+            .descendants(arena)
+            .any(|id| side_effects.contains(&arena[id].get().kind))
+    };
+    if has_side_effects(left) || has_side_effects(right) {
+        return;
+    }
+
+    if subtree_key(arena, left) > subtree_key(arena, right) {
+        left.detach(arena);
+        right.detach(arena);
+        node.checked_prepend(right, arena).expect("Failed to reorder operand");
+        node.checked_append(left, arena).expect("Failed to reorder operand");
+        arena[right].get_mut().role = Some(ROLE_LEFT.to_string());
+        arena[left].get_mut().role = Some(ROLE_RIGHT.to_string());
+    }
+}
+
+fn unwrap(arena: &mut Arena<Node>, node: NodeId) {
+    let role = arena[node].get().role.clone();
+    let children: Vec<_> = node.children(arena).collect();
+    for child in children {
+        child.detach(arena);
+        node.checked_insert_before(child, arena).expect("Failed to unwrap node");
+        let child_value = arena[child].get_mut();
+        if child_value.role.is_none() {
+            child_value.role = role.clone();
+        }
+    }
+    node.remove(arena);
+}
+
+/// Orders subtrees by their content. The role of the subtree's root is left out, as it
+/// differs between the operands being compared.
+fn subtree_key(arena: &Arena<Node>, node: NodeId) -> String {
+    node.descendants(arena)
+        .map(|id| {
+            let depth = id.ancestors(arena).take_while(|a| *a != node).count();
+            let value = arena[id].get();
+            if id == node {
+                format!("{}\n", Node { role: None, ..value.clone() })
+            } else {
+                format!("{}{}\n", " ".repeat(depth), value)
+            }
+        })
+        .collect()
+}
+
+/// Parses an integer literal in C-like syntax (hex, octal, binary, digit separators
+/// and `u`/`l` suffixes) and writes it in decimal with a lowercase suffix.
+fn decimal_integer(literal: &str) -> Option<String> {
+    let literal = literal.replace(['\'', '_'], "");
+    let digits_end = literal.find(INTEGER_SUFFIXES).unwrap_or(literal.len());
+    let (digits, suffix) = literal.split_at(digits_end);
+    if !suffix.chars().all(|c| INTEGER_SUFFIXES.contains(&c)) {
+        return None;
+    }
+
+    let lower = digits.to_ascii_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        u128::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        u128::from_str_radix(binary, 2).ok()?
+    } else if let Some(octal) = lower.strip_prefix("0o") {
+        u128::from_str_radix(octal, 8).ok()?
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        u128::from_str_radix(&lower[1..], 8).ok()?
+    } else {
+        lower.parse::<u128>().ok()?
+    };
+
+    Some(format!("{}{}", value, suffix.to_ascii_lowercase()))
+}
@@ -1,61 +1,296 @@
+use std::collections::HashMap;
 use std::str::Utf8Error;
 use indextree::{Arena, NodeId};
-use tree_sitter::{Language, Parser};
-use kern::{Node, TitError, TitTree};
-use crate::kinds::{insignificant_named_kinds, Kinds, significant_unnamed_kinds};
+use tree_sitter::{InputEdit, Language, Parser, Point, Tree};
+use kern::{Node, SourceMap, Span, TitError, TitTree, ROLE_LEADING_COMMENT, ROLE_TRAILING_COMMENT};
+use crate::kinds::Kinds;
+
+/// Start byte, end byte and kind id of the tree-sitter node a kept node was built from.
+type RangeKey = (usize, usize, u16);
 
 pub struct TitParser {
-    parser: Parser
+    parser: Parser,
+    significant_unnamed_kinds: Kinds,
+    insignificant_named_kinds: Kinds,
+    comment_kinds: Kinds,
+}
+
+/// A parsed source, kept to parse the next version of the same file incrementally with
+/// `TitParser::reparse`.
+pub struct ParsedSource {
+    source: String,
+    tree: Tree,
+    tit_tree: TitTree,
+    source_map: SourceMap,
+    ranges: Ranges,
+    reused_nodes: usize,
+}
+
+impl ParsedSource {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tree(&self) -> &TitTree {
+        &self.tit_tree
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn into_parts(self) -> (TitTree, SourceMap) {
+        (self.tit_tree, self.source_map)
+    }
+
+    /// How many nodes were copied from the previous version instead of being built again.
+    pub fn reused_nodes(&self) -> usize {
+        self.reused_nodes
+    }
 }
 
 impl TitParser {
-    pub fn new(language: Language) -> Result<Self, TitError<'static>> {
+    pub fn new(
+        language: Language,
+        significant_unnamed_kinds: Kinds,
+        insignificant_named_kinds: Kinds,
+        comment_kinds: Kinds,
+    ) -> Result<Self, TitError<'static>> {
         let mut parser = Parser::new();
         match parser.set_language(&language) {
-            Ok(_) => Ok(Self { parser }),
+            Ok(_) => Ok(Self {
+                parser,
+                significant_unnamed_kinds,
+                insignificant_named_kinds,
+                comment_kinds,
+            }),
             Err(_) => Err(TitError("Failed to set language", None)),
         }
     }
     
     pub fn parse(&mut self, source: impl AsRef<[u8]>) -> Result<TitTree, TitError<'static>> {
-        let source_ref = source.as_ref();
-        
-        match self.parser.parse(source_ref, None) {
-            Some(tree) => {
-                let root_node = tree.root_node();
-                let mut arena = Arena::new();
-                let root = arena.new_node(Node {
-                    kind: root_node.kind().to_string(),
-                    value: None,
-                    role: None,
-                });
-                
-                construct_arena(
-                    &root_node,
-                    source_ref,
-                    &mut arena,
-                    &root,
-                    &significant_unnamed_kinds(&self.parser.language().expect("Language should be set set")),
-                    &insignificant_named_kinds(&self.parser.language().expect("Language should be set set")),
-                    None,
-                ).map_err(|_| TitError("Failed to construct arena", None))?;
-                
-                Ok(TitTree::new(arena, root))
-            },
-            None => Err(TitError("Failed to parse source", None)),
+        self.parse_with_source_map(source).map(|(tree, _)| tree)
+    }
+
+    /// Parses the source like `parse`, additionally recording where each node came from so
+    /// the tree can later be rendered without reformatting the parts that did not change.
+    pub fn parse_with_source_map(
+        &mut self,
+        source: impl AsRef<[u8]>,
+    ) -> Result<(TitTree, SourceMap), TitError<'static>> {
+        let source_text = std::str::from_utf8(source.as_ref())
+            .map_err(|_| TitError("Source is not valid UTF-8", None))?;
+        self.parse_source(source_text).map(ParsedSource::into_parts)
+    }
+
+    /// Parses the source like `parse_with_source_map`, keeping what is needed to parse the
+    /// next version of it incrementally.
+    pub fn parse_source(&mut self, source: &str) -> Result<ParsedSource, TitError<'static>> {
+        self.build(source, None)
+    }
+
+    /// Parses a new version of a source. The change from the previous version is handed to
+    /// tree-sitter as an edit, so it only reparses the edited region, and the nodes outside
+    /// of the regions whose structure changed are copied from the previous syntax tree
+    /// instead of being built again.
+    pub fn reparse(&mut self, mut previous: ParsedSource, source: &str) -> Result<ParsedSource, TitError<'static>> {
+        if previous.source == source {
+            previous.reused_nodes = previous.tit_tree.arena().count();
+            return Ok(previous);
+        }
+
+        let edit = byte_edit(&previous.source, source);
+        previous.tree.edit(&edit);
+        self.build(source, Some((&previous, edit)))
+    }
+
+    fn build(
+        &mut self,
+        source: &str,
+        previous: Option<(&ParsedSource, InputEdit)>,
+    ) -> Result<ParsedSource, TitError<'static>> {
+        let old_tree = previous.as_ref().map(|(previous, _)| &previous.tree);
+        let tree = self.parser.parse(source, old_tree)
+            .ok_or(TitError("Failed to parse source", None))?;
+        let reuse = previous.map(|(previous, edit)| Reuse::new(previous, &tree, edit));
+
+        let root_node = tree.root_node();
+        let mut arena = Arena::new();
+        let root_value = Node {
+            kind: root_node.kind().to_string(),
+            value: None,
+            role: None,
+        };
+        let root = arena.new_node(root_value.clone());
+        let mut built = Built {
+            arena,
+            source_map: SourceMap::new(source.to_string()),
+            ranges: Ranges::default(),
+            reused_nodes: 0,
+        };
+        let context = ArenaContext {
+            source: source.as_bytes(),
+            significant_unnamed_kinds: &self.significant_unnamed_kinds,
+            insignificant_named_kinds: &self.insignificant_named_kinds,
+            comment_kinds: &self.comment_kinds,
+            reuse: reuse.as_ref(),
+        };
+
+        construct_arena(
+            &root_node,
+            &context,
+            &mut built,
+            &root,
+            None,
+            Vec::new(),
+        ).map_err(|_| TitError("Failed to construct arena", None))?;
+
+        // The root spans the whole file, so leading and trailing trivia are kept too.
+        let children = root.children(&built.arena).count();
+        built.source_map.insert(root, Span {
+            start: 0,
+            end: source.len(),
+            node: root_value,
+            children,
+        });
+
+        Ok(ParsedSource {
+            source: source.to_string(),
+            tree,
+            tit_tree: TitTree::new(built.arena, root),
+            source_map: built.source_map,
+            ranges: built.ranges,
+            reused_nodes: built.reused_nodes,
+        })
+    }
+}
+
+/// What `construct_arena` builds: the nodes, where they came from, and which tree-sitter
+/// range each was built from.
+struct Built {
+    arena: Arena<Node>,
+    source_map: SourceMap,
+    ranges: Ranges,
+    reused_nodes: usize,
+}
+
+/// The tree-sitter ranges the kept nodes were built from, in both directions.
+#[derive(Default)]
+struct Ranges {
+    keys: HashMap<NodeId, RangeKey>,
+    /// Ranges shared by several nodes map to `None`, as they do not tell which to reuse.
+    nodes: HashMap<RangeKey, Option<NodeId>>,
+}
+
+impl Ranges {
+    fn insert(&mut self, key: RangeKey, node: NodeId) {
+        self.keys.insert(node, key);
+        self.nodes.entry(key)
+            .and_modify(|existing| *existing = None)
+            .or_insert(Some(node));
+    }
+}
+
+/// Finds the nodes of the previous version of a source that the edit left untouched.
+struct Reuse<'a> {
+    previous: &'a ParsedSource,
+    edit: InputEdit,
+    changed: Vec<tree_sitter::Range>,
+}
+
+impl<'a> Reuse<'a> {
+    fn new(previous: &'a ParsedSource, tree: &Tree, edit: InputEdit) -> Self {
+        Self {
+            previous,
+            edit,
+            changed: previous.tree.changed_ranges(tree).collect(),
+        }
+    }
+
+    /// Returns the node previously built from the same source as the given node, if the
+    /// node lies outside of the edited and the structurally changed regions.
+    fn find(&self, node: &tree_sitter::Node) -> Option<NodeId> {
+        let (start, end) = (node.start_byte(), node.end_byte());
+        let touches = |range_start: usize, range_end: usize| start <= range_end && end >= range_start;
+        if touches(self.edit.start_byte, self.edit.new_end_byte)
+            || self.changed.iter().any(|range| touches(range.start_byte, range.end_byte))
+        {
+            return None;
+        }
+
+        let key = (self.to_old(start), self.to_old(end), node.kind_id());
+        self.previous.ranges.nodes.get(&key).copied().flatten()
+    }
+
+    fn to_old(&self, position: usize) -> usize {
+        match position <= self.edit.start_byte {
+            true => position,
+            false => position - self.edit.new_end_byte + self.edit.old_end_byte,
+        }
+    }
+
+    fn to_new(&self, position: usize) -> usize {
+        match position <= self.edit.start_byte {
+            true => position,
+            false => position - self.edit.old_end_byte + self.edit.new_end_byte,
         }
     }
 }
 
-fn construct_arena(
-    node: &tree_sitter::Node,
-    source: &[u8],
-    arena: &mut Arena<Node>,
+/// Describes the change between two versions of a source as a single edit replacing
+/// everything between their common prefix and common suffix.
+fn byte_edit(old: &str, new: &str) -> InputEdit {
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+    let prefix = old_bytes.iter().zip(new_bytes).take_while(|(a, b)| a == b).count();
+    let max_suffix = old_bytes.len().min(new_bytes.len()) - prefix;
+    let suffix = old_bytes.iter().rev().zip(new_bytes.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_end = old_bytes.len() - suffix;
+    let new_end = new_bytes.len() - suffix;
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old_bytes, prefix),
+        old_end_position: point_at(old_bytes, old_end),
+        new_end_position: point_at(new_bytes, new_end),
+    }
+}
+
+fn point_at(source: &[u8], byte: usize) -> Point {
+    let before = &source[..byte];
+    let row = before.iter().filter(|b| **b == b'\n').count();
+    let column = before.iter().rev().take_while(|b| **b != b'\n').count();
+    Point { row, column }
+}
+
+struct ArenaContext<'a> {
+    source: &'a [u8],
+    significant_unnamed_kinds: &'a Kinds,
+    insignificant_named_kinds: &'a Kinds,
+    comment_kinds: &'a Kinds,
+    reuse: Option<&'a Reuse<'a>>,
+}
+
+/// Comments are attached to the node they belong to: the node they trail on the same line,
+/// or else the next node that is kept. `leading_comments` are the comments before `node`
+/// that are still looking for their owner, as `node` itself is not kept.
+///
+/// Attached comments come after the other children of their owner, so adding or removing a
+/// comment does not shift the positions of the owner's children.
+fn construct_arena<'tree>(
+    node: &tree_sitter::Node<'tree>,
+    context: &ArenaContext,
+    built: &mut Built,
     arena_node: &NodeId,
-    significant_unnamed_kinds: &Kinds,
-    insignificant_named_kinds: &Kinds,
     passed_field: Option<&str>,
+    mut leading_comments: Vec<tree_sitter::Node<'tree>>,
 ) -> Result<(), Utf8Error> {
+    let mut previous: Option<(NodeId, tree_sitter::Node)> = None;
+
     let mut cursor = node.walk();
     for (index, child) in node.children(&mut cursor).enumerate() {
         let field = node.field_name_for_child(index as u32);
@@ -65,36 +300,163 @@ fn construct_arena(
 
         let field = field.or(passed_field);
 
-        if !child.is_named() && !significant_unnamed_kinds.contains(child.kind()) {
+        if child.is_named() && context.comment_kinds.contains(child.kind()) {
+            match previous {
+                Some((previous_id, previous_child))
+                    if leading_comments.is_empty()
+                        && previous_child.end_position().row == child.start_position().row
+                        && only_whitespace_between(&previous_child, &child, context.source) =>
+                {
+                    append_comment(&child, context, built, &previous_id, ROLE_TRAILING_COMMENT)?;
+                    let span = built.source_map.span_mut(previous_id).expect("Span should exist");
+                    span.end = child.end_byte();
+                    span.children += 1;
+                }
+                _ => leading_comments.push(child),
+            }
+            continue;
+        }
+
+        if !child.is_named() && !context.significant_unnamed_kinds.contains(child.kind()) {
+            continue;
+        }
+
+        let is_kept = !child.is_named() || !context.insignificant_named_kinds.contains(child.kind());
+        if !is_kept {
+            // The comments go to the first kept node below the child instead.
+            construct_arena(
+                &child,
+                context,
+                built,
+                arena_node,
+                field,
+                std::mem::take(&mut leading_comments),
+            )?;
+            previous = None;
             continue;
         }
 
-        let (new_arena_node, field) =
-            if !child.is_named() || !insignificant_named_kinds.contains(child.kind()) {
-                let child_node = Node {
-                    kind: child.kind().to_string(),
-                    value: if child.child_count() == 0 {
-                        Some(child.utf8_text(source)?.to_string())
-                    } else {
-                        None
-                    },
-                    role: field.map(|f| f.to_string()),
-                };
-                (&arena_node.append_value(child_node, arena), None)
-            } else {
-                (arena_node, field)
+        let new_arena_node = {
+            let child_node = Node {
+                kind: child.kind().to_string(),
+                value: if child.child_count() == 0 {
+                    Some(child.utf8_text(context.source)?.to_string())
+                } else {
+                    None
+                },
+                role: field.map(|f| f.to_string()),
             };
+            arena_node.append_value(child_node, &mut built.arena)
+        };
 
-        construct_arena(
-            &child,
-            source,
-            arena,
-            &new_arena_node,
-            significant_unnamed_kinds,
-            insignificant_named_kinds,
-            field,
-        )?;
+        match context.reuse.and_then(|reuse| Some((reuse, reuse.find(&child)?))) {
+            Some((reuse, old)) => copy_reused(reuse, old, built, new_arena_node),
+            None => construct_arena(
+                &child,
+                context,
+                built,
+                &new_arena_node,
+                None,
+                Vec::new(),
+            )?,
+        }
+
+        let start = leading_comments.first().map_or(child.start_byte(), |comment| comment.start_byte());
+        for comment in leading_comments.drain(..) {
+            append_comment(&comment, context, built, &new_arena_node, ROLE_LEADING_COMMENT)?;
+        }
+
+        built.ranges.insert((child.start_byte(), child.end_byte(), child.kind_id()), new_arena_node);
+        let node = built.arena.get(new_arena_node).expect("Node should exist").get().clone();
+        let children = new_arena_node.children(&built.arena).count();
+        built.source_map.insert(new_arena_node, Span {
+            start,
+            end: child.end_byte(),
+            node,
+            children,
+        });
+        previous = Some((new_arena_node, child));
+    }
+
+    // Comments after the last node (e.g. at the end of a block) trail that node. Without
+    // one, they trail the node they are in.
+    for comment in leading_comments {
+        let owner = match previous {
+            Some((previous_id, _)) => {
+                let span = built.source_map.span_mut(previous_id).expect("Span should exist");
+                span.end = comment.end_byte();
+                span.children += 1;
+                previous_id
+            }
+            None => *arena_node,
+        };
+        append_comment(&comment, context, built, &owner, ROLE_TRAILING_COMMENT)?;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Copies the children of a node of the previous version, along with their spans and
+/// ranges. Comments attached to the node from outside of it are left out, as the caller
+/// attaches them again.
+fn copy_reused(reuse: &Reuse, old: NodeId, built: &mut Built, new: NodeId) {
+    let previous = reuse.previous;
+    let (old_start, old_end, _) = previous.ranges.keys[&old];
+    for old_child in old.children(previous.tit_tree.arena()) {
+        let span = previous.source_map.span(old_child).expect("Span should exist");
+        if span.start < old_start || span.end > old_end {
+            continue;
+        }
+        copy_subtree(reuse, old_child, built, new);
+    }
+}
+
+fn copy_subtree(reuse: &Reuse, old: NodeId, built: &mut Built, parent: NodeId) {
+    let previous = reuse.previous;
+    let old_arena = previous.tit_tree.arena();
+    let new = parent.append_value(old_arena[old].get().clone(), &mut built.arena);
+    built.reused_nodes += 1;
+
+    let span = previous.source_map.span(old).expect("Span should exist");
+    built.source_map.insert(new, Span {
+        start: reuse.to_new(span.start),
+        end: reuse.to_new(span.end),
+        ..span.clone()
+    });
+    if let Some((start, end, kind)) = previous.ranges.keys.get(&old) {
+        built.ranges.insert((reuse.to_new(*start), reuse.to_new(*end), *kind), new);
+    }
+
+    for old_child in old.children(old_arena) {
+        copy_subtree(reuse, old_child, built, new);
+    }
+}
+
+fn append_comment(
+    comment: &tree_sitter::Node,
+    context: &ArenaContext,
+    built: &mut Built,
+    owner: &NodeId,
+    role: &str,
+) -> Result<(), Utf8Error> {
+    let node = Node {
+        kind: comment.kind().to_string(),
+        value: Some(comment.utf8_text(context.source)?.to_string()),
+        role: Some(role.to_string()),
+    };
+    let comment_node = owner.append_value(node.clone(), &mut built.arena);
+    built.source_map.insert(comment_node, Span {
+        start: comment.start_byte(),
+        end: comment.end_byte(),
+        node,
+        children: 0,
+    });
+
+    Ok(())
+}
+
+fn only_whitespace_between(first: &tree_sitter::Node, second: &tree_sitter::Node, source: &[u8]) -> bool {
+    source[first.end_byte()..second.start_byte()]
+        .iter()
+        .all(|byte| byte.is_ascii_whitespace())
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use kern::{TitError, TitTree};
+use serde::Deserialize;
+use tree_sitter::Language;
+
+use crate::kinds::Kinds;
+use crate::normalize::{normalize, Normalization};
+use crate::parser::TitParser;
+use crate::{c, javascript, python, rust};
+
+const CONFIG_FILE: &str = "language.toml";
+
+#[derive(Deserialize)]
+struct LanguageConfig {
+    name: String,
+    grammar: String,
+    extensions: Vec<String>,
+    templates: PathBuf,
+    #[serde(default)]
+    significant_unnamed_kinds: Kinds,
+    #[serde(default)]
+    insignificant_named_kinds: Kinds,
+    #[serde(default = "default_comment_kinds")]
+    comment_kinds: Kinds,
+    #[serde(default)]
+    normalizations: Vec<Normalization>,
+}
+
+pub struct LanguageEntry {
+    pub name: String,
+    pub language: Language,
+    pub extensions: Vec<String>,
+    pub template_dir: PathBuf,
+    pub significant_unnamed_kinds: Kinds,
+    pub insignificant_named_kinds: Kinds,
+    pub comment_kinds: Kinds,
+    pub normalizations: Vec<Normalization>,
+}
+
+impl LanguageEntry {
+    pub fn parser(&self) -> Result<TitParser, TitError<'static>> {
+        TitParser::new(
+            self.language.clone(),
+            self.significant_unnamed_kinds.clone(),
+            self.insignificant_named_kinds.clone(),
+            self.comment_kinds.clone(),
+        )
+    }
+
+    /// Applies the language's normalization rules, see [`Normalization`].
+    pub fn normalize(&self, tree: &mut TitTree) {
+        normalize(tree, &self.normalizations);
+    }
+}
+
+#[derive(Default)]
+pub struct LanguageRegistry {
+    languages: Vec<LanguageEntry>,
+    extensions: HashMap<String, usize>,
+}
+
+impl LanguageRegistry {
+    /// Loads every language that has a config file in a subdirectory of `lang_dir`.
+    pub fn load(lang_dir: &Path) -> Result<Self, TitError<'static>> {
+        let mut registry = Self::default();
+
+        let entries = fs::read_dir(lang_dir)
+            .map_err(|e| TitError("Failed to read language directory", Some(e)))?;
+        let mut config_paths = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(CONFIG_FILE))
+            .filter(|path| path.exists())
+            .collect::<Vec<_>>();
+        config_paths.sort();
+
+        for config_path in config_paths {
+            registry.register(load_entry(&config_path)?);
+        }
+
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, entry: LanguageEntry) {
+        let index = self.languages.len();
+        for extension in &entry.extensions {
+            self.extensions.insert(extension.clone(), index);
+        }
+        self.languages.push(entry);
+    }
+
+    pub fn for_extension(&self, extension: &str) -> Option<&LanguageEntry> {
+        self.extensions
+            .get(extension)
+            .map(|index| &self.languages[*index])
+    }
+
+    pub fn for_path(&self, path: &Path) -> Option<&LanguageEntry> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.for_extension(extension))
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &LanguageEntry> {
+        self.languages.iter()
+    }
+}
+
+fn load_entry(config_path: &Path) -> Result<LanguageEntry, TitError<'static>> {
+    let content = fs::read_to_string(config_path)
+        .map_err(|e| TitError("Failed to read language config", Some(e)))?;
+    let config: LanguageConfig =
+        toml::from_str(&content).map_err(|_| TitError("Failed to parse language config", None))?;
+    let language =
+        grammar(&config.grammar).ok_or(TitError("Unknown grammar in language config", None))?;
+    let language_dir = config_path
+        .parent()
+        .expect("Config file should be in a language directory");
+
+    Ok(LanguageEntry {
+        name: config.name,
+        language,
+        extensions: config.extensions,
+        template_dir: language_dir.join(config.templates),
+        significant_unnamed_kinds: config.significant_unnamed_kinds,
+        insignificant_named_kinds: config.insignificant_named_kinds,
+        comment_kinds: config.comment_kinds,
+        normalizations: config.normalizations,
+    })
+}
+
+fn default_comment_kinds() -> Kinds {
+    Kinds::from(["comment".to_string()])
+}
+
+fn grammar(name: &str) -> Option<Language> {
+    match name {
+        "c" => Some(c!()),
+        "rust" => Some(rust!()),
+        "python" => Some(python!()),
+        "javascript" => Some(javascript!()),
+        _ => None,
+    }
+}
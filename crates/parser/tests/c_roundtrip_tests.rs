@@ -0,0 +1,75 @@
+mod common;
+
+use common::{c_corpus_files, c_parser_and_templater};
+
+#[test]
+fn test_c_corpus_round_trips() {
+    let (mut parser, mut templater) = c_parser_and_templater();
+
+    let files = c_corpus_files();
+    assert!(!files.is_empty());
+
+    for file in files {
+        let source = std::fs::read_to_string(&file).unwrap();
+        let tree = parser.parse(&source).unwrap();
+        let rendered = templater.render_tree(&tree).unwrap();
+        let reparsed = parser.parse(&rendered).unwrap();
+
+        assert_eq!(
+            tree,
+            reparsed,
+            "{} does not round-trip, rendered as:\n{}",
+            file.display(),
+            rendered
+        );
+    }
+}
+
+#[test]
+fn test_c_corpus_renders_original_source() {
+    let (mut parser, mut templater) = c_parser_and_templater();
+
+    for file in c_corpus_files() {
+        let source = std::fs::read_to_string(&file).unwrap();
+        let (tree, source_map) = parser.parse_with_source_map(&source).unwrap();
+        let rendered = templater.render_tree_preserving(&tree, &source_map).unwrap();
+
+        assert_eq!(source, rendered, "{} was reformatted", file.display());
+    }
+}
+
+#[test]
+fn test_render_preserves_formatting_around_changes() {
+    let src1 = r#"
+/* Counter helpers. */
+int   counter ( void )
+{
+    // Starts at zero.
+    int value = 0;
+
+    value  +=  1;
+    return value;
+}
+"#;
+
+    let src2 = r#"
+/* Counter helpers. */
+int   counter ( void )
+{
+    // Starts at zero.
+    int value = 42;
+
+    value  +=  1;
+    return value * 2;
+}
+"#;
+
+    let (mut parser, mut templater) = c_parser_and_templater();
+
+    let (mut tree1, source_map) = parser.parse_with_source_map(src1).unwrap();
+    let tree2 = parser.parse(src2).unwrap();
+    tree1.apply_changes(&tree1.detect_changes(&tree2));
+
+    let rendered = templater.render_tree_preserving(&tree1, &source_map).unwrap();
+    assert_eq!(rendered, src2);
+}
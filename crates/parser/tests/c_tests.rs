@@ -1,5 +1,8 @@
-use parser::c;
-use parser::parser::TitParser;
+use kern::{Change, ROLE_LEADING_COMMENT, ROLE_TRAILING_COMMENT};
+
+mod common;
+
+use common::c_parser;
 
 #[test]
 fn test_apply_01() {
@@ -15,7 +18,7 @@ fn test_apply_01() {
         }
     "#;
     
-    let mut parser = TitParser::new(c!()).unwrap();
+    let mut parser = c_parser();
     let mut tree1 = parser.parse(src1).unwrap();
     let tree2 = parser.parse(src2).unwrap();
     
@@ -38,7 +41,7 @@ fn test_apply_02() {
         }
     "#;
     
-    let mut parser = TitParser::new(c!()).unwrap();
+    let mut parser = c_parser();
     let mut tree1 = parser.parse(src1).unwrap();
     let tree2 = parser.parse(src2).unwrap();
     
@@ -61,7 +64,7 @@ fn test_apply_03() {
         }
     "#;
     
-    let mut parser = TitParser::new(c!()).unwrap();
+    let mut parser = c_parser();
     let mut tree1 = parser.parse(src1).unwrap();
     let tree2 = parser.parse(src2).unwrap();
     
@@ -93,7 +96,7 @@ fn test_apply_04() {
         }
     "#;
     
-    let mut parser = TitParser::new(c!()).unwrap();
+    let mut parser = c_parser();
     let mut tree1 = parser.parse(src1).unwrap();
     let tree2 = parser.parse(src2).unwrap();
     
@@ -128,7 +131,7 @@ fn test_apply_05() {
         }
     "#;
 
-    let mut parser = TitParser::new(c!()).unwrap();
+    let mut parser = c_parser();
     let tree1 = parser.parse(src1).unwrap();
     let tree2 = parser.parse(src2).unwrap();
 
@@ -139,6 +142,77 @@ fn test_apply_05() {
     println!("{:?}", tree1);
 }
 
+#[test]
+fn test_doc_comment_change() {
+    let src1 = r#"
+        /* Returns the answer. */
+        int answer() {
+            return 42; // not 41
+        }
+    "#;
+
+    let src2 = r#"
+        /* Returns the answer to everything. */
+        int answer() {
+            return 42; // not 41
+        }
+    "#;
+
+    let mut parser = c_parser();
+    let mut tree1 = parser.parse(src1).unwrap();
+    let tree2 = parser.parse(src2).unwrap();
+
+    let changes = tree1.detect_changes(&tree2);
+
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Update(path, node) => {
+            // Comments follow the type, declarator and body of the function.
+            assert_eq!(path, &vec![0, 0, 3]);
+            assert_eq!(node.role.as_deref(), Some(ROLE_LEADING_COMMENT));
+        }
+        _ => panic!("Expected an update of the doc comment"),
+    }
+
+    tree1.apply_changes(&changes);
+    assert_eq!(tree1, tree2);
+}
+
+#[test]
+fn test_added_comment_keeps_positions() {
+    let src1 = r#"
+        int main() {
+            int x = 1;
+            return x;
+        }
+    "#;
+
+    let src2 = r#"
+        int main() {
+            // The start value.
+            int x = 1;
+            return x;
+            /* nothing after this */
+        }
+    "#;
+
+    let mut parser = c_parser();
+    let tree1 = parser.parse(src1).unwrap();
+    let tree2 = parser.parse(src2).unwrap();
+
+    let changes = tree1.detect_changes(&tree2);
+
+    assert_eq!(changes.len(), 2);
+    let roles: Vec<_> = changes
+        .iter()
+        .map(|change| match change {
+            Change::Addition(_, node) => node.role.clone().unwrap(),
+            _ => panic!("Expected only additions of comments"),
+        })
+        .collect();
+    assert_eq!(roles, vec![ROLE_LEADING_COMMENT, ROLE_TRAILING_COMMENT]);
+}
+
 // #[test]
 // fn test_render_01() {
 //     let src = r#"
@@ -147,7 +221,7 @@ fn test_apply_05() {
 //         }
 //     "#;
 // 
-//     let mut parser = TitParser::new(c!()).unwrap();
+//     let mut parser = c_parser();
 //     let tree = parser.parse(src).unwrap();
 // 
 //     let mut templater = TitTemplater::new(std::path::Path::new("/home/phaulson/Desktop/tit/lang/c/templates"));
@@ -168,7 +242,7 @@ fn test_apply_05() {
 //         }
 //     "#;
 // 
-//     let mut parser = TitParser::new(c!()).unwrap();
+//     let mut parser = c_parser();
 //     let tree = parser.parse(src).unwrap();
 // 
 //     println!("{:?}", tree);
@@ -197,7 +271,7 @@ fn test_apply_05() {
 //         }
 //     "#;
 // 
-//     let mut parser = TitParser::new(c!()).unwrap();
+//     let mut parser = c_parser();
 //     let tree = parser.parse(src).unwrap();
 // 
 //     let mut templater = TitTemplater::new(std::path::Path::new("/home/phaulson/Desktop/tit/lang/c/templates"));
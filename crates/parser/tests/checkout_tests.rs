@@ -0,0 +1,151 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{repository, write_file};
+use kern::{Commit, PersonStamp, TitRepository, TitTree, EMPTY_HASH};
+
+const MAIN_C: &str = r#"#include <stdio.h>
+
+/* Prints a greeting. */
+int   main ( void )
+{
+    printf( "hello\n" ) ;   // Trailing comment.
+
+
+    return 0;
+}
+"#;
+
+const LIB_RS: &str = r#"
+pub fn add(a: i32,b: i32)->i32 {
+        a+b // Sum.
+}
+"#;
+
+/// Commits all changes of the working directory to the current branch, as `create change`
+/// does.
+fn commit(repository: &TitRepository, message: &str) -> String {
+    let before = repository.signed_tree();
+    let after = repository.current_tree();
+    let changes = kern::detect_tree_changes(&before, &after);
+    repository
+        .store_blobs(&after, &kern::blobs_of_changes(&changes))
+        .unwrap();
+
+    let stamp = PersonStamp {
+        name: "Ada".to_string(),
+        email: "ada@example.com".to_string(),
+        timestamp: 0,
+        offset: 0,
+    };
+    let commit = Commit::new(
+        message.to_string(),
+        changes,
+        repository.state().current_commit_id().cloned(),
+        after.get_root().map(|root| root.hash).unwrap_or(EMPTY_HASH),
+        stamp.clone(),
+        stamp,
+    );
+    let id = repository.write_commit(&commit).unwrap();
+
+    let mut state = repository.state();
+    state
+        .branches
+        .insert(state.current.branch.clone(), id.clone());
+    repository.set_state(state);
+    repository.set_signed_tree(after);
+    id
+}
+
+/// Copies the commits and blobs of a repository into a new one in `dir`, as a clone
+/// downloads them, and checks out the head of its branch.
+fn clone(from: &TitRepository, dir: &Path) -> TitRepository {
+    fs::create_dir_all(dir).unwrap();
+    let repository = repository(dir);
+    repository.init("test", "none", "main").unwrap();
+    for id in from.commit_ids() {
+        repository.write_commit(&from.read_commit(&id)).unwrap();
+    }
+
+    let head = from.state().current_commit_id().cloned().unwrap();
+    let (source, target) = (from.blob_store(), repository.blob_store());
+    for blob in from.blobs_of_commits(&from.commit_ids()) {
+        for hash in source.objects(&blob) {
+            target
+                .write_object(&source.read_object(&hash).unwrap())
+                .unwrap();
+        }
+    }
+
+    let mut state = repository.state();
+    state.branches = from.state().branches;
+    repository.set_state(state);
+    repository.checkout(&head);
+    repository
+}
+
+#[test]
+fn test_clone_keeps_formatting_of_parsed_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (origin_dir, clone_dir) = (
+        temp_dir.path().join("origin"),
+        temp_dir.path().join("clone"),
+    );
+    write_file(&origin_dir, "src/main.c", MAIN_C);
+    write_file(&origin_dir, "src/lib.rs", LIB_RS);
+    write_file(&origin_dir, "notes.txt", "Not parsed.\n");
+
+    let origin = repository(&origin_dir);
+    origin.init("test", "none", "main").unwrap();
+    let first = commit(&origin, "First");
+
+    // Both files are parsed, so their formatting is not part of the syntax tree.
+    let tree = TitTree::from(&origin.tree_at(&first));
+    let kinds: Vec<_> = tree
+        .arena()
+        .iter()
+        .map(|node| node.get().kind.as_str())
+        .collect();
+    assert!(kinds.contains(&"translation_unit"));
+    assert!(kinds.contains(&"source_file"));
+
+    let cloned = clone(&origin, &clone_dir);
+    for path in ["src/main.c", "src/lib.rs", "notes.txt"] {
+        assert_eq!(
+            fs::read_to_string(clone_dir.join(path)).unwrap(),
+            fs::read_to_string(origin_dir.join(path)).unwrap(),
+            "{} was reformatted",
+            path
+        );
+    }
+
+    // Formatting changes are committed too, and checked out in place of the old file.
+    write_file(
+        &origin_dir,
+        "src/lib.rs",
+        "pub fn add(a: i32, b: i32) -> i32 {\n    a + b // Sum.\n}\n",
+    );
+    let second = commit(&origin, "Reformat");
+    let changes = origin.read_commit(&second).changes;
+    assert!(!changes.is_empty());
+    assert!(!kern::blobs_of_changes(&changes).is_empty());
+
+    let cloned_again = clone(&origin, &temp_dir.path().join("again"));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("again/src/lib.rs")).unwrap(),
+        fs::read_to_string(origin_dir.join("src/lib.rs")).unwrap()
+    );
+
+    fs::remove_file(clone_dir.join("src/main.c")).unwrap();
+    cloned.checkout(&first);
+    assert_eq!(
+        fs::read_to_string(clone_dir.join("src/main.c")).unwrap(),
+        MAIN_C
+    );
+    assert!(kern::file_statuses(&cloned.signed_tree(), &cloned.current_tree()).is_empty());
+    assert!(
+        kern::file_statuses(&cloned_again.signed_tree(), &cloned_again.current_tree()).is_empty()
+    );
+}
@@ -0,0 +1,86 @@
+// Each test binary uses a different part of these helpers.
+#![allow(dead_code)]
+
+use kern::{ContentFormat, TitRepository, TitTree};
+use parser::parser::TitParser;
+use parser::registry::LanguageRegistry;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use templater::TitTemplater;
+
+pub fn registry() -> LanguageRegistry {
+    let lang_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../lang");
+    LanguageRegistry::load(&lang_dir).unwrap()
+}
+
+pub fn c_parser() -> TitParser {
+    registry().for_extension("c").unwrap().parser().unwrap()
+}
+
+pub fn c_parser_and_templater() -> (TitParser, TitTemplater) {
+    let registry = registry();
+    let language = registry.for_extension("c").unwrap();
+    let templater = TitTemplater::new(language.template_dir.clone());
+    (language.parser().unwrap(), templater)
+}
+
+/// The C files that must survive a parse and render unchanged, sorted by name.
+pub fn c_corpus_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/c");
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Parses and renders files with the languages of the repository, like the command line
+/// does.
+pub struct LanguageFormat {
+    registry: LanguageRegistry,
+}
+
+impl LanguageFormat {
+    pub fn new() -> Self {
+        Self {
+            registry: registry(),
+        }
+    }
+}
+
+impl ContentFormat for LanguageFormat {
+    fn parse(&self, path: &Path, content: &str) -> Option<TitTree> {
+        let language = self.registry.for_path(path)?;
+        language.parser().ok()?.parse(content).ok()
+    }
+
+    fn render(&self, path: &Path, tree: &TitTree, previous: Option<&str>) -> Option<String> {
+        let language = self.registry.for_path(path)?;
+        let mut templater = TitTemplater::new(language.template_dir.clone());
+        match previous {
+            Some(previous) => {
+                let mut parser = language.parser().ok()?;
+                let (mut original, source_map) = parser.parse_with_source_map(previous).ok()?;
+                original.apply_changes(&original.detect_changes(tree));
+                templater
+                    .render_tree_preserving(&original, &source_map)
+                    .ok()
+            }
+            None => templater.render_tree(tree).ok(),
+        }
+    }
+}
+
+/// Opens the repository in `dir`, tracking source files as syntax trees.
+pub fn repository(dir: &Path) -> TitRepository {
+    TitRepository::new(dir.to_path_buf()).with_content_format(Arc::new(LanguageFormat::new()))
+}
+
+/// Writes a file below `dir`, creating the directories it lies in.
+pub fn write_file(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
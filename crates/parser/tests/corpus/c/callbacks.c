@@ -0,0 +1,64 @@
+/* Event dispatch with function pointer tables. */
+#include <stddef.h>
+#include <stdio.h>
+
+#pragma once
+
+#define COUNT_OF(array) (sizeof(array) / sizeof((array)[0]))
+
+typedef void (*handler_fn)(void *context, int event);
+typedef enum { EVENT_OPEN, EVENT_CLOSE, EVENT_COUNT } event_t;
+
+struct dispatcher {
+    handler_fn handlers[EVENT_COUNT];
+    void *context;
+    const char *name;
+};
+
+// Registers a handler, replacing any previous one.
+static void dispatcher_register(struct dispatcher *d, event_t event, handler_fn fn)
+{
+    if (event < 0 || event >= EVENT_COUNT) {
+        return;
+    }
+    d->handlers[event] = fn;
+}
+
+static void log_event(void *context, int event)
+{
+    const char *label = event == EVENT_OPEN ? "open" : "close";
+    fprintf(stderr, "[%s] " "event %s\n", (const char *)context, label);
+}
+
+static size_t name_offset(void)
+{
+    return offsetof(struct dispatcher, name);
+}
+
+static int *first_positive(int *values, size_t count)
+{
+    for (size_t i = 0; i < count; i++) {
+        if (values[i] > 0) {
+            return &values[i];
+        }
+    }
+    return NULL;
+}
+
+int dispatch(struct dispatcher *d, int event)
+{
+    handler_fn fn = d->handlers[event];
+    int values[] = { -1, 0, 3 };
+    int *found = first_positive(values, COUNT_OF(values));
+    char quote = '\'';
+    _Bool ok = true;
+
+    if (fn == NULL)
+        goto missing;
+    // Call through the table.
+    fn(d->context, event);
+    return found != NULL && *found == 3 && quote != '"' && ok;
+
+missing:
+    return (int)sizeof(int *) - (int)sizeof(void (*)(void));
+}
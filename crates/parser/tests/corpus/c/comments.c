@@ -0,0 +1,33 @@
+/*
+ * Ring buffer of bytes.
+ */
+#include <stddef.h>
+
+#define RING_SIZE 64 /* must be a power of two */
+
+struct ring {
+    unsigned char data[RING_SIZE]; // storage
+    size_t head;                   // next write
+    size_t tail;                   // next read
+};
+
+/**
+ * Pushes a byte, dropping the oldest one when the ring is full.
+ */
+void ring_push(struct ring *ring, unsigned char byte)
+{
+    ring->data[ring->head] = byte; /* overwrite */
+    ring->head = (ring->head + 1) & (RING_SIZE - 1);
+
+    // Drop the oldest byte when full.
+    if (ring->head == ring->tail) {
+        ring->tail = (ring->tail + 1) & (RING_SIZE - 1);
+    }
+}
+
+/// Returns the number of buffered bytes.
+size_t ring_length(const struct ring *ring)
+{
+    return (ring->head - ring->tail) & (RING_SIZE - 1);
+    // unreachable
+}
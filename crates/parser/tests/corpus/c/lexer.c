@@ -0,0 +1,106 @@
+#ifndef LEXER_H
+#define LEXER_H
+
+#include <ctype.h>
+#include <string.h>
+
+enum token_kind {
+    TOKEN_EOF,
+    TOKEN_NUMBER = 1,
+    TOKEN_IDENT,
+    TOKEN_KEYWORD,
+    TOKEN_PUNCT = 0x10,
+};
+
+union token_value {
+    long number;
+    char ident[32];
+    char punct;
+};
+
+struct token {
+    enum token_kind kind;
+    union token_value value;
+    unsigned int line : 16;
+    unsigned int column : 16;
+};
+
+#endif
+
+#ifdef DEBUG
+#define TRACE(msg) fprintf(stderr, "%s\n", msg)
+#else
+#define TRACE(msg)
+#endif
+
+#if defined(__GNUC__) && __GNUC__ >= 4
+#define UNUSED __attribute__((unused))
+#elif defined(_MSC_VER)
+#define UNUSED
+#else
+#define UNUSED
+#endif
+
+static const char *const keywords[] = { "if", "else", "while", "return" };
+
+static int is_keyword(const char *text)
+{
+    for (unsigned long i = 0; i < sizeof(keywords) / sizeof(keywords[0]); i++) {
+        if (strcmp(text, keywords[i]) == 0) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+int next_token(const char **cursor, struct token *out)
+{
+    const char *p = *cursor;
+
+    while (isspace((unsigned char)*p)) {
+        if (*p == '\n') {
+            out->line++;
+            out->column = 0;
+        }
+        p++;
+    }
+
+    switch (*p) {
+    case '\0':
+        out->kind = TOKEN_EOF;
+        break;
+    case '+':
+    case '-':
+    case '*':
+    case '/':
+        out->kind = TOKEN_PUNCT;
+        out->value.punct = *p++;
+        break;
+    default:
+        if (isdigit((unsigned char)*p)) {
+            long number = 0;
+            do {
+                number = number * 10 + (*p - '0');
+                p++;
+            } while (isdigit((unsigned char)*p));
+            out->kind = TOKEN_NUMBER;
+            out->value.number = number;
+        } else if (isalpha((unsigned char)*p) || *p == '_') {
+            size_t length = 0;
+            while ((isalnum((unsigned char)*p) || *p == '_') && length < sizeof(out->value.ident) - 1) {
+                out->value.ident[length++] = *p++;
+            }
+            out->value.ident[length] = '\0';
+            out->kind = is_keyword(out->value.ident) ? TOKEN_KEYWORD : TOKEN_IDENT;
+        } else {
+            goto error;
+        }
+    }
+
+    *cursor = p;
+    return 0;
+
+error:
+    TRACE("unexpected character");
+    return -1;
+}
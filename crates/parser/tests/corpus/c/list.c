@@ -0,0 +1,76 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include "list.h"
+
+#define LIST_INITIAL_CAPACITY 16
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+
+typedef struct node {
+    int value;
+    struct node *next;
+} node_t;
+
+typedef struct {
+    node_t *head;
+    size_t length;
+} list_t;
+
+static node_t *node_new(int value)
+{
+    node_t *node = malloc(sizeof(node_t));
+    if (node == NULL) {
+        return NULL;
+    }
+    node->value = value;
+    node->next = NULL;
+    return node;
+}
+
+list_t *list_new(void)
+{
+    list_t *list = calloc(1, sizeof *list);
+    return list;
+}
+
+int list_push(list_t *list, int value)
+{
+    node_t *node = node_new(value);
+    if (!node)
+        return -1;
+
+    node->next = list->head;
+    list->head = node;
+    list->length++;
+    return 0;
+}
+
+int list_sum(const list_t *list)
+{
+    int sum = 0;
+    for (const node_t *it = list->head; it != NULL; it = it->next) {
+        sum += it->value;
+    }
+    return sum;
+}
+
+void list_free(list_t *list)
+{
+    node_t *it = list->head;
+    while (it) {
+        node_t *next = it->next;
+        free(it);
+        it = next;
+    }
+    free(list);
+}
+
+int main(int argc, char **argv)
+{
+    list_t *list = list_new();
+    for (int i = 0; i < argc; ++i) {
+        list_push(list, atoi(argv[i]));
+    }
+    printf("sum: %d, max: %d\n", list_sum(list), MAX(1, 2));
+    list_free(list);
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,84 @@
+#include <math.h>
+#include <stdint.h>
+
+#define ROWS 4
+#define COLS 4
+
+typedef double matrix_t[ROWS][COLS];
+typedef int (*compare_fn)(const void *, const void *);
+
+struct point {
+    int32_t x, y;
+};
+
+extern int verbose;
+static volatile uint8_t flags = 0;
+static unsigned long long counter;
+
+static inline double clamp(double value, double low, double high)
+{
+    return value < low ? low : value > high ? high : value;
+}
+
+void matrix_identity(matrix_t m)
+{
+    for (int row = 0; row < ROWS; row++)
+        for (int col = 0; col < COLS; col++)
+            m[row][col] = row == col ? 1.0 : 0.0;
+}
+
+void matrix_scale(matrix_t m, double factor)
+{
+    int row = 0, col;
+    while (row < ROWS) {
+        col = 0;
+        while (col < COLS) {
+            m[row][col] *= factor;
+            m[row][col] = clamp(m[row][col], -1e9, 1e9);
+            col += 1;
+        }
+        row++;
+    }
+}
+
+int compare_points(const void *a, const void *b)
+{
+    const struct point *pa = (const struct point *)a;
+    const struct point *pb = b;
+    int dx = pa->x - pb->x;
+    return dx != 0 ? dx : (pa->y - pb->y);
+}
+
+struct point make_point(int x, int y)
+{
+    struct point p = { .x = x, .y = y };
+    struct point q = (struct point){ 1, 2 };
+    int values[3] = { [0] = 1, [2] = 3 };
+    flags |= 1u << 3;
+    flags &= ~(1u << 2);
+    counter = counter + 1, counter <<= 1;
+    q.x = values[0] + values[2] % 2 - -x;
+    return sizeof(p) > 0 && !verbose ? p : q;
+}
+
+double distance(struct point a, struct point b)
+{
+    double dx = (double)(a.x - b.x);
+    double dy = (double)(a.y - b.y);
+    return sqrt(dx * dx + dy * dy);
+}
+
+int sum_all(int count, ...)
+{
+    int total = 0;
+    compare_fn fn = compare_points;
+    (void)fn;
+    if (count <= 0) return 0;
+    else if (count > 100) return -1;
+    for (;;) {
+        if (--count < 0) break;
+        if (count % 2) continue;
+        total ^= count;
+    }
+    return total;
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{repository, write_file};
+use kern::TitTree;
+
+const MAIN_C: &str = r#"#include <stdio.h>
+
+/* Prints a greeting. */
+int   main ( void )
+{
+    printf( "hello\n" ) ;   // Trailing comment.
+
+
+    return 0;
+}
+"#;
+
+const LIB_RS: &str = r#"
+pub fn add(a: i32,b: i32)->i32 {
+        a+b // Sum.
+}
+"#;
+
+/// Runs git with a fixed identity and dates and without the config of the user.
+fn run_git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "Ada")
+        .env("GIT_AUTHOR_EMAIL", "ada@example.com")
+        .env("GIT_AUTHOR_DATE", "1700000000 +0200")
+        .env("GIT_COMMITTER_NAME", "Bob")
+        .env("GIT_COMMITTER_EMAIL", "bob@example.com")
+        .env("GIT_COMMITTER_DATE", "1700000100 -0530")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_git_round_trip_keeps_parsed_files() {
+    if Command::new("git").arg("--version").output().is_err() {
+        println!("Skipping, git is not installed");
+        return;
+    }
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    let source = dir.join("source");
+    fs::create_dir_all(&source).unwrap();
+    run_git(&source, &["init", "--quiet", "--initial-branch=main"]);
+    write_file(&source, "src/main.c", MAIN_C);
+    write_file(&source, "src/lib.rs", LIB_RS);
+    write_file(&source, "README.md", "hello\n");
+    run_git(&source, &["add", "--all"]);
+    run_git(&source, &["commit", "--quiet", "-m", "First"]);
+    // A change to the code and one to the formatting only.
+    write_file(
+        &source,
+        "src/main.c",
+        &MAIN_C.replace("return 0;", "return  1 ;"),
+    );
+    write_file(&source, "src/lib.rs", &LIB_RS.replace("a+b", "a + b"));
+    run_git(&source, &["add", "--all"]);
+    run_git(&source, &["commit", "--quiet", "-m", "Second"]);
+
+    let tit = dir.join("tit");
+    fs::create_dir_all(&tit).unwrap();
+    let repository = repository(&tit);
+    repository.init("test", "none", "main").unwrap();
+    assert_eq!(repository.import_git(&source).unwrap(), (2, vec![]));
+
+    // Both files are tracked as syntax trees, not as blobs.
+    let tree = TitTree::from(&repository.signed_tree());
+    let kinds: Vec<_> = tree
+        .arena()
+        .iter()
+        .map(|node| node.get().kind.as_str())
+        .collect();
+    assert!(kinds.contains(&"translation_unit"));
+    assert!(kinds.contains(&"source_file"));
+
+    for path in ["src/main.c", "src/lib.rs"] {
+        assert_eq!(
+            fs::read(tit.join(path)).unwrap(),
+            fs::read(source.join(path)).unwrap(),
+            "{} was reformatted on import",
+            path
+        );
+    }
+
+    // Equal trees mean every file of every commit was exported with the same bytes.
+    let exported = dir.join("exported");
+    assert_eq!(repository.export_git(&exported).unwrap(), 2);
+    for revision in ["main~1^{tree}", "main^{tree}"] {
+        assert_eq!(
+            run_git(&exported, &["rev-parse", revision]),
+            run_git(&source, &["rev-parse", revision]),
+            "{} differs",
+            revision
+        );
+    }
+}
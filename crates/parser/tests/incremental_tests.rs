@@ -0,0 +1,63 @@
+mod common;
+
+use common::{c_corpus_files, c_parser_and_templater};
+
+/// Successive versions of a file: a changed type, an inserted comment, a deleted line and
+/// the original again.
+fn edits(source: &str) -> Vec<String> {
+    let retyped = source.replacen("int ", "long ", 1);
+
+    let mut lines: Vec<_> = retyped.lines().collect();
+    lines.insert(lines.len() / 2, "/* inserted */");
+    let commented = lines.join("\n");
+
+    lines.remove(lines.len() * 2 / 3);
+    let deleted = lines.join("\n");
+
+    vec![retyped.clone(), commented, deleted, source.to_string()]
+}
+
+#[test]
+fn test_reparse_matches_full_parse() {
+    let (mut incremental, mut templater) = c_parser_and_templater();
+    let (mut full, _) = c_parser_and_templater();
+
+    for file in c_corpus_files() {
+        let source = std::fs::read_to_string(&file).unwrap();
+        let mut parsed = incremental.parse_source(&source).unwrap();
+
+        for version in edits(&source) {
+            parsed = incremental.reparse(parsed, &version).unwrap();
+            let expected = full.parse(&version).unwrap();
+            assert_eq!(*parsed.tree(), expected, "{} parsed differently", file.display());
+            assert!(parsed.reused_nodes() > 0, "{} was built again", file.display());
+
+            let rendered = templater
+                .render_tree_preserving(parsed.tree(), parsed.source_map())
+                .unwrap();
+            assert_eq!(rendered, version, "{} rendered differently", file.display());
+        }
+    }
+}
+
+#[test]
+fn test_reparse_reuses_unchanged_subtrees() {
+    let (mut parser, _) = c_parser_and_templater();
+    let functions: String = (0..50)
+        .map(|i| format!("int f{i}(int a) {{\n    // Doubles.\n    return a * {i};\n}}\n\n"))
+        .collect();
+
+    let parsed = parser.parse_source(&functions).unwrap();
+    assert_eq!(parsed.reused_nodes(), 0);
+    let nodes = parsed.tree().arena().count();
+
+    // Only the function around the edit is built again.
+    let edited = functions.replacen("return a * 25;", "return a + 25;", 1);
+    let parsed = parser.reparse(parsed, &edited).unwrap();
+    assert_eq!(*parsed.tree(), parser.parse(&edited).unwrap());
+    assert!(parsed.reused_nodes() > nodes * 9 / 10);
+    assert!(parsed.reused_nodes() < nodes);
+
+    let parsed = parser.reparse(parsed, &edited).unwrap();
+    assert_eq!(parsed.reused_nodes(), parsed.tree().arena().count());
+}
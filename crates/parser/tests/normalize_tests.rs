@@ -0,0 +1,89 @@
+mod common;
+
+use common::registry;
+
+#[test]
+fn test_cosmetic_changes_normalize_away() {
+    let src1 = r#"
+        int mask(int flags, int size) {
+            int total = (size * 4) + flags;
+            if (total == 0x10) {
+                return (flags & 0xFFu);
+            }
+            return total;
+        }
+    "#;
+
+    let src2 = r#"
+        int mask(int flags, int size) {
+            int total = flags + 4 * size;
+            if (16 == total)
+                return 255U & flags;
+            return total;
+        }
+    "#;
+
+    let registry = registry();
+    let language = registry.for_extension("c").unwrap();
+    let mut parser = language.parser().unwrap();
+    let mut tree1 = parser.parse(src1).unwrap();
+    let mut tree2 = parser.parse(src2).unwrap();
+
+    assert!(!tree1.detect_changes(&tree2).is_empty());
+
+    language.normalize(&mut tree1);
+    language.normalize(&mut tree2);
+
+    assert_eq!(tree1.detect_changes(&tree2).len(), 0);
+}
+
+#[test]
+fn test_real_changes_survive_normalization() {
+    let src1 = "int f(int a, int b) { return a - b; }";
+    let src2 = "int f(int a, int b) { return b - a; }";
+
+    let registry = registry();
+    let language = registry.for_extension("c").unwrap();
+    let mut parser = language.parser().unwrap();
+    let mut tree1 = parser.parse(src1).unwrap();
+    let mut tree2 = parser.parse(src2).unwrap();
+
+    language.normalize(&mut tree1);
+    language.normalize(&mut tree2);
+
+    assert_eq!(tree1.detect_changes(&tree2).len(), 2);
+}
+
+#[test]
+fn test_operands_of_the_same_kind_are_ordered() {
+    let src1 = "int f(int a, int b) { return a * b; }";
+    let src2 = "int f(int a, int b) { return b * a; }";
+
+    let registry = registry();
+    let language = registry.for_extension("c").unwrap();
+    let mut parser = language.parser().unwrap();
+    let mut tree1 = parser.parse(src1).unwrap();
+    let mut tree2 = parser.parse(src2).unwrap();
+
+    language.normalize(&mut tree1);
+    language.normalize(&mut tree2);
+
+    assert_eq!(tree1.detect_changes(&tree2).len(), 0);
+}
+
+#[test]
+fn test_operands_with_calls_keep_their_order() {
+    let src1 = "int f(void) { return g() * h(); }";
+    let src2 = "int f(void) { return h() * g(); }";
+
+    let registry = registry();
+    let language = registry.for_extension("c").unwrap();
+    let mut parser = language.parser().unwrap();
+    let mut tree1 = parser.parse(src1).unwrap();
+    let mut tree2 = parser.parse(src2).unwrap();
+
+    language.normalize(&mut tree1);
+    language.normalize(&mut tree2);
+
+    assert!(!tree1.detect_changes(&tree2).is_empty());
+}
@@ -0,0 +1,25 @@
+use kern::Query;
+
+mod common;
+
+use common::c_parser;
+
+fn count_matches(query: &str, src: &str) -> usize {
+    let mut parser = c_parser();
+    let tree = parser.parse(src).unwrap();
+    let query: Query = query.parse().unwrap();
+    query.matches(&tree).len()
+}
+
+#[test]
+fn test_query_calls() {
+    let src = r#"
+        void *f(int n) { void *p = malloc(n); free(malloc(1)); return p; }
+    "#;
+
+    assert_eq!(count_matches(r#"(call_expression function: "malloc")"#, src), 2);
+    assert_eq!(count_matches(r#"(call_expression function: (identifier "free"))"#, src), 1);
+    assert_eq!(count_matches(r#"(call_expression arguments: (_ (call_expression)))"#, src), 1);
+    assert_eq!(count_matches(r#"(call_expression arguments: "malloc")"#, src), 0);
+    assert_eq!(count_matches(r#"(_ declarator: "f")"#, src), 1);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::registry;
+use std::path::Path;
+
+#[test]
+fn test_lookup_by_extension() {
+    let registry = registry();
+
+    assert_eq!(registry.for_extension("c").unwrap().name, "c");
+    assert_eq!(registry.for_extension("h").unwrap().name, "c");
+    assert_eq!(registry.for_extension("rs").unwrap().name, "rust");
+    assert_eq!(registry.for_extension("py").unwrap().name, "python");
+    assert_eq!(registry.for_extension("js").unwrap().name, "javascript");
+    assert!(registry.for_extension("txt").is_none());
+    assert_eq!(
+        registry.for_path(Path::new("src/main.rs")).unwrap().name,
+        "rust"
+    );
+}
+
+#[test]
+fn test_parse_each_language() {
+    let registry = registry();
+    let sources = [
+        ("c", "int main() { return 1 + 2; }"),
+        ("rs", "fn main() { let x = 1 + 2; }"),
+        ("py", "def main():\n    return 1 + 2\n"),
+        ("js", "function main() { return 1 + 2; }"),
+    ];
+
+    for (extension, source) in sources {
+        let language = registry.for_extension(extension).unwrap();
+        let mut parser = language.parser().unwrap();
+        let mut tree1 = parser.parse(source).unwrap();
+        let tree2 = parser.parse(source.replace('2', "3")).unwrap();
+
+        let changes = tree1.detect_changes(&tree2);
+        assert_eq!(changes.len(), 1);
+
+        tree1.apply_changes(&changes);
+        assert_eq!(tree1, tree2);
+    }
+}
@@ -0,0 +1,72 @@
+use kern::{summarize_renames, Rename};
+
+mod common;
+
+use common::c_parser;
+
+fn renames(src1: &str, src2: &str) -> (Vec<Rename>, usize) {
+    let mut parser = c_parser();
+    let tree1 = parser.parse(src1).unwrap();
+    let tree2 = parser.parse(src2).unwrap();
+
+    let changes = tree1.detect_changes(&tree2);
+    let renames = summarize_renames(&tree1, &changes);
+    let remaining = changes
+        .iter()
+        .filter(|change| !renames.iter().any(|rename| rename.covers(change)))
+        .count();
+
+    (renames, remaining)
+}
+
+#[test]
+fn test_rename_function() {
+    let src1 = r#"
+        int count_items(int *items) { return items[0]; }
+        int main() { int items[1] = {0}; return count_items(items) + count_items(items); }
+    "#;
+    let src2 = r#"
+        int item_count(int *items) { return items[0]; }
+        int main() { int items[1] = {0}; return item_count(items) + item_count(items); }
+    "#;
+
+    let (renames, remaining) = renames(src1, src2);
+
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames[0].from, "count_items");
+    assert_eq!(renames[0].to, "item_count");
+    assert_eq!(renames[0].scope, "translation_unit");
+    assert_eq!(renames[0].paths.len(), 3);
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn test_rename_local_variable() {
+    let src1 = r#"
+        int f(int n) { int total = n; total += 1; return total; }
+        int g(int n) { int total = n; return total; }
+    "#;
+    let src2 = r#"
+        int f(int n) { int sum = n; sum += 1; return sum; }
+        int g(int n) { int total = n; return total; }
+    "#;
+
+    let (renames, remaining) = renames(src1, src2);
+
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames[0].to_string(), "Rename symbol total -> sum in scope f (3 occurrences)");
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn test_rename_parameter() {
+    let src1 = "int f(int n) { return n * n; }";
+    let src2 = "int f(int size) { return size * size; }";
+
+    let (renames, remaining) = renames(src1, src2);
+
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames[0].scope, "f");
+    assert_eq!(renames[0].paths.len(), 3);
+    assert_eq!(remaining, 0);
+}
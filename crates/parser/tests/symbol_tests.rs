@@ -0,0 +1,34 @@
+use kern::find_symbol;
+
+mod common;
+
+use common::c_parser;
+
+fn parse(src: &str) -> kern::TitTree {
+    let mut parser = c_parser();
+    parser.parse(src).unwrap()
+}
+
+#[test]
+fn test_find_symbol() {
+    let tree = parse(
+        r#"
+        struct point { int x; int y; };
+        int *lookup(struct point p);
+        int *lookup(struct point p) { return 0; }
+        int main() { struct point p = {1, 2}; return *lookup(p); }
+    "#,
+    );
+    let arena = tree.arena();
+
+    let lookup = find_symbol(&tree, "lookup");
+    assert_eq!(lookup.len(), 1);
+    assert_eq!(arena[lookup[0].1].get().kind, "function_definition");
+
+    let point = find_symbol(&tree, "point");
+    assert_eq!(point.len(), 1);
+    assert_eq!(arena[point[0].1].get().kind, "struct_specifier");
+
+    assert_eq!(find_symbol(&tree, "main").len(), 1);
+    assert!(find_symbol(&tree, "p").is_empty());
+}
@@ -43,14 +43,72 @@ pub fn handle(
                 }
                 network::TitClientMessage::DownloadFile { id } => {
                     println!("Received DownloadFile message: {}", id);
-                    let commit = kern::Commit::new("commit1".to_string(), vec![], 0, None);
-                    let response = network::TitServerMessage::CommitFile { commit };
+                    let response = if repository.commit_ids().contains(&id) {
+                        let commit = repository.read_commit(&id);
+                        network::TitServerMessage::CommitFile {
+                            commit: Box::new(commit),
+                        }
+                    } else {
+                        network::TitServerMessage::Error
+                    };
                     network::write_message(&mut stream, response)?;
                 }
                 network::TitClientMessage::UploadChanges { changes } => {
                     println!("Received UploadFile message: {}", changes);
-                    repository.write_commit(&changes);
-                    network::write_message(&mut stream, network::TitServerMessage::Hello)?;
+                    // Commits of unknown versions have no ID, so they are rejected as well.
+                    let response = match changes.verify() {
+                        kern::Verification::Bad => {
+                            println!("Rejected commit with a bad signature");
+                            TitServerMessage::Error
+                        }
+                        _ => match repository.write_commit(&changes) {
+                            Ok(_) => TitServerMessage::Hello,
+                            Err(_) => {
+                                println!("Rejected commit of unknown version {}", changes.version);
+                                TitServerMessage::Error
+                            }
+                        },
+                    };
+                    network::write_message(&mut stream, response)?;
+                }
+                network::TitClientMessage::DownloadTags => {
+                    println!("Received DownloadTags message");
+                    let tags = repository.tags().into_values().collect();
+                    network::write_message(&mut stream, TitServerMessage::Tags { tags })?;
+                }
+                network::TitClientMessage::UploadTag { tag } => {
+                    println!("Received UploadTag message: {}", tag);
+                    let response = match accepts_tag(&repository, &tag) {
+                        true => {
+                            repository.write_tag(&tag);
+                            TitServerMessage::Ok
+                        }
+                        false => TitServerMessage::Error,
+                    };
+                    network::write_message(&mut stream, response)?;
+                }
+                network::TitClientMessage::OfferBlobs { hashes } => {
+                    println!("Received OfferBlobs message: {} objects", hashes.len());
+                    let blobs = repository.blob_store();
+                    let hashes = hashes
+                        .into_iter()
+                        .filter(|hash| !blobs.contains(hash))
+                        .collect();
+                    network::write_message(&mut stream, TitServerMessage::RequestBlobs { hashes })?;
+                }
+                network::TitClientMessage::UploadBlob { data } => {
+                    let response = match repository.blob_store().write_object(&data) {
+                        Ok(_) => TitServerMessage::Ok,
+                        Err(_) => TitServerMessage::Error,
+                    };
+                    network::write_message(&mut stream, response)?;
+                }
+                network::TitClientMessage::DownloadBlob { hash } => {
+                    let response = match repository.blob_store().read_object(&hash) {
+                        Some(data) => TitServerMessage::Blob { data },
+                        None => TitServerMessage::Error,
+                    };
+                    network::write_message(&mut stream, response)?;
                 }
                 network::TitClientMessage::CreateRepository { name } => {
                     let response = match storage.create_repository(&name) {
@@ -59,18 +117,17 @@ pub fn handle(
                     };
                     network::write_message(&mut stream, response)?;
                 }
-                network::TitClientMessage::OfferContent { commits, branches } => {
+                network::TitClientMessage::OfferContent { commits } => {
                     let missing_commits = set_difference(&commits, &repository.commit_ids());
                     let response = network::TitServerMessage::RequestUpload {
                         commits: missing_commits,
                     };
                     network::write_message(&mut stream, response)?;
-
-                    let mut state = repository.state();
-                    for (name, commit_id) in branches {
-                        state.branches.insert(name, commit_id);
-                    }
-                    repository.set_state(state);
+                }
+                network::TitClientMessage::UpdateBranch { name, commit_id } => {
+                    println!("Received UpdateBranch message: {} -> {}", name, commit_id);
+                    let response = update_branch(&repository, name, commit_id);
+                    network::write_message(&mut stream, response)?;
                 }
                 _ => {
                     println!("Received unexpected message");
@@ -88,6 +145,83 @@ pub fn handle(
     Ok(())
 }
 
+/// Moves a branch to a commit, if the commit was uploaded before, the branch only moves
+/// forward and, on branches that require signatures, every added commit is signed by a
+/// trusted key. This is the only way branches of a server change.
+fn update_branch(
+    repository: &kern::TitRepository,
+    name: String,
+    commit_id: String,
+) -> TitServerMessage {
+    if !repository.commit_ids().contains(&commit_id) {
+        println!("Rejected update to unknown commit {}", commit_id);
+        return TitServerMessage::Error;
+    }
+    let mut state = repository.state();
+    let is_fast_forward = match state.branches.get(&name) {
+        Some(old_id) if old_id != kern::NO_COMMIT => {
+            repository.history(&commit_id).contains(old_id)
+        }
+        _ => true,
+    };
+    if !is_fast_forward {
+        println!("Rejected update of {} that is not a fast-forward", name);
+        return TitServerMessage::Error;
+    }
+
+    let unsigned = match state.requires_signatures(&name) {
+        true => unsigned_commits(repository, &state, &name, &commit_id),
+        false => vec![],
+    };
+    if !unsigned.is_empty() {
+        println!("Rejected update of {} with unsigned commits", name);
+        return TitServerMessage::UnsignedCommits { commits: unsigned };
+    }
+
+    state.branches.insert(name, commit_id);
+    repository.set_state(state);
+    TitServerMessage::Ok
+}
+
+/// Tags never move, so a tag is only accepted if there is no tag of that name yet, or the
+/// same one. It must name an existing commit and must not carry a bad signature.
+fn accepts_tag(repository: &kern::TitRepository, tag: &kern::Tag) -> bool {
+    if !kern::Tag::is_valid_name(&tag.name) || tag.verify() == kern::Verification::Bad {
+        return false;
+    }
+    if !repository.commit_ids().contains(&tag.target) {
+        return false;
+    }
+    match repository.read_tag(&tag.name) {
+        Some(existing) => existing == *tag,
+        None => true,
+    }
+}
+
+/// Commits a branch update would add to the branch that are not signed by a key trusted on
+/// the branch. A signature alone proves nothing, as anyone can generate a key.
+fn unsigned_commits(
+    repository: &kern::TitRepository,
+    state: &kern::RepositoryState,
+    branch: &str,
+    commit_id: &str,
+) -> Vec<String> {
+    let known = state
+        .branches
+        .get(branch)
+        .map(|old_id| repository.history(old_id))
+        .unwrap_or_default();
+    repository
+        .history(commit_id)
+        .into_iter()
+        .take_while(|id| !known.contains(id))
+        .filter(|id| match repository.read_commit(id).verify() {
+            kern::Verification::Good(key) => !state.trusts_key(branch, &key),
+            _ => true,
+        })
+        .collect()
+}
+
 pub fn set_difference<T: PartialEq + Clone>(a: &[T], b: &[T]) -> Vec<T> {
     let mut difference = vec![];
 
@@ -99,3 +233,123 @@ pub fn set_difference<T: PartialEq + Clone>(a: &[T], b: &[T]) -> Vec<T> {
 
     difference
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use kern::{Commit, PersonStamp, EMPTY_HASH};
+    use network::{NetworkError, TitClient};
+
+    use super::handle;
+    use crate::repositorystorage::RepositoryStorage;
+
+    /// Serves the repositories of a storage on a free port and returns its address.
+    fn serve(storage: RepositoryStorage) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let _ = handle(stream.unwrap(), storage.clone());
+            }
+        });
+        address
+    }
+
+    fn commit(message: &str, predecessor: Option<&Commit>) -> Commit {
+        let stamp = PersonStamp {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            timestamp: 1_700_000_000_000,
+            offset: 0,
+        };
+        Commit::new(
+            message.to_string(),
+            vec![],
+            predecessor.map(|commit| commit.get_id().unwrap()),
+            EMPTY_HASH,
+            stamp.clone(),
+            stamp,
+        )
+    }
+
+    /// Uploads commits like sync does, offering them first.
+    fn upload(client: &mut TitClient, commits: &[&Commit]) {
+        let ids = commits.iter().map(|commit| commit.get_id().unwrap());
+        let missing = client.offer_content(ids.collect()).unwrap();
+        let commits = commits
+            .iter()
+            .filter(|commit| missing.contains(&commit.get_id().unwrap()))
+            .map(|commit| (*commit).clone())
+            .collect();
+        client.upload_changes(commits).unwrap();
+    }
+
+    #[test]
+    fn test_branches_only_move_forward() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RepositoryStorage::new(dir.path().to_path_buf());
+        let repository = storage.create_repository("test").unwrap();
+        let mut client = TitClient::new(&serve(storage), "test").unwrap();
+
+        let first = commit("first", None);
+        let second = commit("second", Some(&first));
+        let other = commit("other", Some(&first));
+        upload(&mut client, &[&first, &second]);
+        let first_id = first.get_id().unwrap();
+        let second_id = second.get_id().unwrap();
+
+        client
+            .update_branch("main".to_string(), first_id.clone())
+            .unwrap();
+        client
+            .update_branch("main".to_string(), second_id.clone())
+            .unwrap();
+
+        // Rewinding the branch or moving it to a commit the server does not have fails.
+        let result = client.update_branch("main".to_string(), first_id);
+        assert!(matches!(result, Err(NetworkError::Rejected)));
+        let result = client.update_branch("main".to_string(), other.get_id().unwrap());
+        assert!(matches!(result, Err(NetworkError::Rejected)));
+
+        // A diverged head is rejected even once the server has its commits.
+        upload(&mut client, &[&first, &other]);
+        let result = client.update_branch("main".to_string(), other.get_id().unwrap());
+        assert!(matches!(result, Err(NetworkError::Rejected)));
+        assert_eq!(repository.state().branches.get("main"), Some(&second_id));
+    }
+
+    #[test]
+    fn test_signed_branch_only_takes_trusted_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RepositoryStorage::new(dir.path().to_path_buf());
+        let repository = storage.create_repository("test").unwrap();
+        let mut state = repository.state();
+        state.signed_branches.insert("main".to_string());
+        repository.set_state(state);
+        let mut client = TitClient::new(&serve(storage), "test").unwrap();
+
+        let key = kern::generate_signing_key();
+        let mut signed = commit("signed", None);
+        signed.sign(&key).unwrap();
+        let unsigned = commit("unsigned", Some(&signed));
+        upload(&mut client, &[&signed, &unsigned]);
+        let signed_id = signed.get_id().unwrap();
+        let unsigned_id = unsigned.get_id().unwrap();
+
+        let result = client.update_branch("main".to_string(), signed_id.clone());
+        assert!(matches!(result, Err(NetworkError::Unsigned(ids)) if ids == [signed_id.clone()]));
+
+        let mut state = repository.state();
+        let trusted = state.trusted_keys.entry("main".to_string()).or_default();
+        trusted.insert(kern::public_key_hex(&key));
+        repository.set_state(state);
+        client
+            .update_branch("main".to_string(), signed_id.clone())
+            .unwrap();
+
+        let result = client.update_branch("main".to_string(), unsigned_id.clone());
+        assert!(matches!(result, Err(NetworkError::Unsigned(ids)) if ids == [unsigned_id]));
+        assert_eq!(repository.state().branches.get("main"), Some(&signed_id));
+    }
+}
@@ -18,8 +18,8 @@ impl TitServer {
 
     pub fn run(&self) {
         let address: String = format!("{}:{}", self.host, self.port);
-        let listener =
-            TcpListener::bind(address).expect(&format!("Server failed to bind to {}", self.port));
+        let listener = TcpListener::bind(address)
+            .unwrap_or_else(|_| panic!("Server failed to bind to {}", self.port));
         println!("Server listening on port 6969");
         loop {
             match listener.accept() {
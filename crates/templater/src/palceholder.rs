@@ -1,3 +1,6 @@
+/// Fix key that matches any present node, regardless of its kind.
+const ANY_KIND: &str = "_";
+
 #[derive(Clone)]
 pub struct Fix {
     pub key: String,
@@ -5,6 +8,17 @@ pub struct Fix {
     pub is_negated: bool,
 }
 
+impl Fix {
+    pub fn applies_to(&self, kind: &str) -> bool {
+        let matches = if self.key == ANY_KIND {
+            !kind.is_empty()
+        } else {
+            self.key == kind
+        };
+        matches != self.is_negated
+    }
+}
+
 #[derive(Clone)]
 pub struct Placeholder {
     pub name: String,
@@ -20,10 +34,10 @@ impl Placeholder {
         let name = caps.name("role").expect("Role should exist").as_str().to_string();
 
         let mut prefix = None;
-        if let Some(_) = caps.name("prefix") {
+        if caps.name("prefix").is_some() {
             let prefix_key = caps.name("prefix_key").expect("Prefix key should exist").as_str().to_string();
             let prefix_value = caps.name("prefix_value").expect("Prefix value should exist").as_str().to_string();
-            let is_negated = caps.name("prefix_not").is_some();
+            let is_negated = caps.name("prefix_not").is_some_and(|m| !m.as_str().is_empty());
             prefix = Some(Fix {
                 key: prefix_key,
                 value: prefix_value,
@@ -32,10 +46,10 @@ impl Placeholder {
         }
         
         let mut suffix = None;
-        if let Some(_) = caps.name("suffix") {
+        if caps.name("suffix").is_some() {
             let suffix_key = caps.name("suffix_key").expect("Suffix key should exist").as_str().to_string();
             let suffix_value = caps.name("suffix_value").expect("Suffix value should exist").as_str().to_string();
-            let is_negated = caps.name("suffix_not").is_some();
+            let is_negated = caps.name("suffix_not").is_some_and(|m| !m.as_str().is_empty());
             suffix = Some(Fix {
                 key: suffix_key,
                 value: suffix_value,
@@ -44,7 +58,7 @@ impl Placeholder {
         }
         
         let mut delimiter = "".to_string();
-        if let Some(_) = caps.name("delim") {
+        if caps.name("delim").is_some() {
             delimiter = caps.name("delim_value").expect("Delimiter value should exist").as_str().to_string();
         }
 
@@ -60,7 +74,7 @@ impl Placeholder {
     pub fn render(&self, values: &[String], kind: &str) -> String {
         let mut result = String::new();
         if let Some(prefix) = &self.prefix {
-            if prefix.key == kind && !prefix.is_negated || prefix.key != kind && prefix.is_negated {
+            if prefix.applies_to(kind) {
                 result = prefix.value.clone();
             }
         }
@@ -68,7 +82,7 @@ impl Placeholder {
         result.push_str(&values.join(&self.delimiter));
         
         if let Some(suffix) = &self.suffix {
-            if suffix.key == kind && !suffix.is_negated || suffix.key != kind && suffix.is_negated {
+            if suffix.applies_to(kind) {
                 result.push_str(&suffix.value);
             }
         }
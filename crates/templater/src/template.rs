@@ -6,7 +6,7 @@ use std::collections::HashMap;
 use std::path::Path;
 
 lazy_static! {
-    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\$(?P<placeholder>(?P<role>[a-zA-Z_]+)(\[(?P<prefix>prefix) ?(?P<prefix_not>!?)(?P<prefix_key>[a-zA-Z_]+)=(?P<prefix_value>.+?)])?(\[(?P<suffix>suffix) ?(?P<suffix_not>!?)(?P<suffix_key>[a-zA-Z_]+)=(?P<suffix_value>.+?)])?(\[(?P<delim>delim) ?(?P<delim_value>.+?)])?)\$").expect("Regex should be valid");
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\$(?P<placeholder>(?P<role>[a-zA-Z_#]+)(\[(?P<prefix>prefix) ?(?P<prefix_not>!?)(?P<prefix_key>[a-zA-Z_]+)=(?P<prefix_value>.+?)])?(\[(?P<suffix>suffix) ?(?P<suffix_not>!?)(?P<suffix_key>[a-zA-Z_]+)=(?P<suffix_value>.+?)])?(\[(?P<delim>delim) ?(?P<delim_value>.+?)])?)\$").expect("Regex should be valid");
 }
 
 #[derive(Clone)]
@@ -37,6 +37,10 @@ impl Template {
         Ok(template)
     }
 
+    pub fn has_placeholder(&self, name: &str) -> bool {
+        self.placeholders.iter().any(|p| p.name == name)
+    }
+
     pub fn render(&self, replacements: &HashMap<&str, (Vec<String>, &str)>) -> String {
         let mut result = self.content.clone();
         let empty_replacement = (Vec::new(), "");
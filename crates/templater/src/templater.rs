@@ -1,13 +1,16 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use kern::{Node, TitError, TitTree};
+use kern::{Node, SourceMap, TitError, TitTree, ROLE_LEADING_COMMENT, ROLE_TRAILING_COMMENT};
 
 use crate::template::Template;
 
+/// Template suffix used for nodes that have no role in their parent.
+const NO_ROLE: &str = "none";
+
 pub struct TitTemplater {
     folder: PathBuf,
-    templates: HashMap<String, Template>,
+    templates: HashMap<String, Option<Template>>,
 }
 
 impl TitTemplater {
@@ -18,35 +21,56 @@ impl TitTemplater {
         }
     }
 
-    pub fn render_tree(&mut self, tree: &TitTree) -> Result<String, TitError> {
+    pub fn render_tree(&mut self, tree: &TitTree) -> Result<String, TitError<'_>> {
         let root = tree.root()?;
-        self.render_node(&root, tree)
+        self.render_node(root, tree, None)
+    }
+
+    /// Renders the tree reproducing the original source for every subtree that is unchanged
+    /// since it was parsed, so templates only apply to the regions that changed.
+    pub fn render_tree_preserving(
+        &mut self,
+        tree: &TitTree,
+        source_map: &SourceMap,
+    ) -> Result<String, TitError<'_>> {
+        let root = tree.root()?;
+        self.render_node(root, tree, Some(source_map))
     }
 
     fn render_node(
         &mut self,
         node: &indextree::Node<Node>,
         tree: &TitTree,
+        source_map: Option<&SourceMap>,
     ) -> Result<String, TitError<'static>> {
+        if let Some(source_map) = source_map {
+            if let Some(rendered) = self.render_original(node, tree, source_map)? {
+                return Ok(rendered);
+            }
+        }
+
         let node_value = node.get();
 
-        let template = match self.templates.get(&node_value.kind) {
-            Some(template) => template.clone(),
-            None => {
-                let new_template = Template::from_path(&self.folder, &node_value.kind);
-                match new_template {
-                    Ok(template) => {
-                        self.templates.insert(node_value.kind.clone(), template);
-                        self.templates
-                            .get(&node_value.kind)
-                            .expect("Template should exist")
-                            .clone()
-                    }
-                    Err(_) if node_value.value.is_some() => Template::value_default(),
-                    _ => Template::children_default()
-                }
-            }
+        // A template named `<kind>.<role>` takes precedence when the first child has that role,
+        // which keeps prefix and postfix forms like `++i` and `i++` apart.
+        let first_role = tree
+            .children(node)?
+            .find(|child| !is_comment_role(&child.get().role, ROLE_LEADING_COMMENT))
+            .and_then(|child| child.get().role.clone());
+        // Nodes without a role stand on their own (e.g. `struct s {};` at the top level),
+        // so they may use a `<kind>@none` template instead.
+        let own_template = match node_value.role {
+            Some(_) => None,
+            None => self.template(&format!("{}@{}", node_value.kind, NO_ROLE)),
         };
+        let template = first_role
+            .and_then(|role| self.template(&format!("{}.{}", node_value.kind, role)))
+            .or(own_template)
+            .or_else(|| self.template(&node_value.kind))
+            .unwrap_or_else(|| match node_value.value {
+                Some(_) => Template::value_default(),
+                None => Template::children_default(),
+            });
 
         let mut replacements = HashMap::new();
         
@@ -54,18 +78,94 @@ impl TitTemplater {
             insert_content(&mut replacements, "__value__", value.clone(), &node_value.kind);
         }
         
+        let mut leading_comments = String::new();
+        let mut trailing_comments = String::new();
+
         for child in tree.children(node)? {
             let child_value = child.get();
-            let child_result = self.render_node(&child, tree)?;
+            let child_result = self.render_node(child, tree, source_map)?;
 
-            if let Some(role) = &child.get().role {
+            // Leading comments start on their own line so they are not taken as trailing
+            // comments of whatever is rendered before them.
+            if is_comment_role(&child_value.role, ROLE_LEADING_COMMENT) {
+                leading_comments.push('\n');
+                leading_comments.push_str(&child_result);
+            } else if is_comment_role(&child_value.role, ROLE_TRAILING_COMMENT) {
+                trailing_comments.push(' ');
+                trailing_comments.push_str(&child_result);
+            } else if let Some(role) = &child.get().role {
                 insert_content(&mut replacements, role, child_result, &child_value.kind);
+            } else if template.has_placeholder(&child_value.kind) {
+                insert_content(&mut replacements, &child_value.kind, child_result, &child_value.kind);
             } else {
                 insert_content(&mut replacements, "__children__", child_result, &child_value.kind);
             }
         }
 
-        Ok(template.render(&replacements))
+        let mut rendered = template.render(&replacements);
+        if !trailing_comments.is_empty() && rendered.ends_with(char::is_whitespace) {
+            rendered.pop();
+        }
+
+        Ok(format!("{}{}{}", leading_comments, rendered, trailing_comments))
+    }
+
+    /// Copies the node's original source, rendering only its children in between, as long as
+    /// the node itself and the number and order of its children are unchanged.
+    fn render_original(
+        &mut self,
+        node: &indextree::Node<Node>,
+        tree: &TitTree,
+        source_map: &SourceMap,
+    ) -> Result<Option<String>, TitError<'static>> {
+        let span = match tree.node_id(node).and_then(|id| source_map.span(id)) {
+            Some(span) if span.node == *node.get() => span,
+            _ => return Ok(None),
+        };
+
+        let children: Vec<_> = tree.children(node)?.collect();
+        if children.len() != span.children {
+            return Ok(None);
+        }
+
+        let mut child_spans = Vec::new();
+        let mut position = span.start;
+        for child in &children {
+            let child_span = match tree.node_id(child).and_then(|id| source_map.span(id)) {
+                Some(child_span) if child_span.end <= span.end => child_span,
+                _ => return Ok(None),
+            };
+            // Attached comments come after the other children, but lie anywhere in the source.
+            if !is_comment(&child.get().role) {
+                if child_span.start < position {
+                    return Ok(None);
+                }
+                position = child_span.end;
+            }
+            child_spans.push((*child, child_span));
+        }
+        child_spans.sort_by_key(|(_, child_span)| child_span.start);
+
+        let mut result = String::new();
+        let mut position = span.start;
+        for (child, child_span) in child_spans {
+            if child_span.start < position {
+                return Ok(None);
+            }
+            result.push_str(source_map.text(position, child_span.start));
+            result.push_str(&self.render_node(child, tree, Some(source_map))?);
+            position = child_span.end;
+        }
+        result.push_str(source_map.text(position, span.end));
+
+        Ok(Some(result))
+    }
+
+    fn template(&mut self, name: &str) -> Option<Template> {
+        self.templates
+            .entry(name.to_string())
+            .or_insert_with(|| Template::from_path(&self.folder, name).ok())
+            .clone()
     }
 }
 
@@ -73,3 +173,11 @@ fn insert_content<'a>(replacements: &mut HashMap<&'a str, (Vec<String>, &'a str)
     let (values, _) = replacements.entry(key).or_insert((Vec::new(), kind));
     values.push(value);
 }
+
+fn is_comment_role(role: &Option<String>, comment_role: &str) -> bool {
+    role.as_deref() == Some(comment_role)
+}
+
+fn is_comment(role: &Option<String>) -> bool {
+    is_comment_role(role, ROLE_LEADING_COMMENT) || is_comment_role(role, ROLE_TRAILING_COMMENT)
+}